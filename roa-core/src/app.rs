@@ -112,12 +112,20 @@ impl<S, T> App<S, T> {
     }
 }
 
+impl<S, T> App<S, T> {
+    /// Get the executor of this app.
+    #[inline]
+    pub fn exec(&self) -> &Executor {
+        &self.exec
+    }
+}
+
 impl<S> App<S, ()> {
     /// Construct an application with custom runtime.
     pub fn with_exec(state: S, exec: impl 'static + Send + Sync + Spawn) -> Self {
         Self {
             service: (),
-            exec: Executor(Arc::new(exec)),
+            exec: Executor::new(exec),
             state,
         }
     }
@@ -162,7 +170,9 @@ where
     }
 
     /// Make a fake http service for test.
-    #[cfg(test)]
+    ///
+    /// The remote address of fake service is `127.0.0.1:0`,
+    /// use `HttpService::set_remote_addr` to change it.
     pub fn http_service(&self) -> HttpService<S, E>
     where
         S: Clone,
@@ -237,6 +247,7 @@ where
 }
 
 impl<S, E> HttpService<S, E> {
+    /// Construct a http service.
     pub fn new(
         endpoint: Arc<E>,
         remote_addr: SocketAddr,
@@ -251,6 +262,13 @@ impl<S, E> HttpService<S, E> {
         }
     }
 
+    /// Set the socket addr of client.
+    #[inline]
+    pub fn set_remote_addr(&mut self, remote_addr: SocketAddr) -> &mut Self {
+        self.remote_addr = remote_addr;
+        self
+    }
+
     /// Receive a request then return a response.
     /// The entry point of http service.
     pub async fn serve(self, req: Request) -> Response
@@ -1,9 +1,11 @@
 use futures::channel::oneshot::{channel, Receiver};
-use futures::task::{Context, Poll};
+use futures::task::{Context, Poll, Waker};
 use hyper::rt;
+use std::collections::hash_map::{Entry, HashMap};
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 /// Future Object
 pub type FutureObj = Pin<Box<dyn 'static + Send + Future<Output = ()>>>;
@@ -22,21 +24,56 @@ pub trait Spawn {
 
 /// A type implementing hyper::rt::Executor
 #[derive(Clone)]
-pub struct Executor(pub(crate) Arc<dyn 'static + Send + Sync + Spawn>);
+pub struct Executor {
+    spawner: Arc<dyn 'static + Send + Sync + Spawn>,
+    tasks: Arc<Tasks>,
+}
 
 /// A handle that awaits the result of a task.
 pub struct JoinHandle<T>(Receiver<T>);
 
+/// A future that resolves when all tasks spawned by an executor are complete.
+pub struct Idle {
+    tasks: Arc<Tasks>,
+    key: usize,
+}
+
+/// Counter of running tasks.
+#[derive(Default)]
+struct Tasks {
+    counter: AtomicUsize,
+    next_key: AtomicUsize,
+    wakers: Mutex<HashMap<usize, Waker>>,
+}
+
+/// A guard to decrease counter when a task is complete.
+struct TaskGuard(Arc<Tasks>);
+
 impl Executor {
-    /// Spawn a future by app runtime
+    /// Construct an executor by a spawner.
+    #[inline]
+    pub(crate) fn new(spawner: impl 'static + Send + Sync + Spawn) -> Self {
+        Self {
+            spawner: Arc::new(spawner),
+            tasks: Arc::new(Tasks::default()),
+        }
+    }
+
+    /// Spawn a future by app runtime.
+    ///
+    /// With feature `tracing`, the future is instrumented by current span.
     #[inline]
     pub fn spawn<Fut>(&self, fut: Fut) -> JoinHandle<Fut::Output>
     where
         Fut: 'static + Send + Future,
         Fut::Output: 'static + Send,
     {
+        #[cfg(feature = "tracing")]
+        let fut = tracing::Instrument::instrument(fut, tracing::Span::current());
         let (sender, recv) = channel();
-        self.0.spawn(Box::pin(async move {
+        let guard = self.track();
+        self.spawner.spawn(Box::pin(async move {
+            let _guard = guard;
             if sender.send(fut.await).is_err() {
                 // handler is dropped, do nothing.
             };
@@ -44,21 +81,98 @@ impl Executor {
         JoinHandle(recv)
     }
 
-    /// Spawn a blocking task by app runtime
+    /// Spawn a blocking task by app runtime.
+    ///
+    /// With feature `tracing`, the task runs in current span.
     #[inline]
     pub fn spawn_blocking<T, R>(&self, task: T) -> JoinHandle<R>
     where
         T: 'static + Send + FnOnce() -> R,
         R: 'static + Send,
     {
+        #[cfg(feature = "tracing")]
+        let span = tracing::Span::current();
         let (sender, recv) = channel();
-        self.0.spawn_blocking(Box::new(|| {
+        let guard = self.track();
+        self.spawner.spawn_blocking(Box::new(move || {
+            let _guard = guard;
+            #[cfg(feature = "tracing")]
+            let _enter = span.enter();
             if sender.send(task()).is_err() {
                 // handler is dropped, do nothing.
             };
         }));
         JoinHandle(recv)
     }
+
+    /// Get the number of running tasks spawned by this executor,
+    /// including connections served by hyper.
+    #[inline]
+    pub fn tasks(&self) -> usize {
+        self.tasks.counter.load(Ordering::SeqCst)
+    }
+
+    /// Wait until all tasks spawned by this executor are complete.
+    #[inline]
+    pub fn idle(&self) -> Idle {
+        Idle {
+            tasks: self.tasks.clone(),
+            key: self.tasks.next_key.fetch_add(1, Ordering::SeqCst),
+        }
+    }
+
+    /// Count a new task.
+    #[inline]
+    fn track(&self) -> TaskGuard {
+        self.tasks.counter.fetch_add(1, Ordering::SeqCst);
+        TaskGuard(self.tasks.clone())
+    }
+}
+
+impl Drop for TaskGuard {
+    #[inline]
+    fn drop(&mut self) {
+        if self.0.counter.fetch_sub(1, Ordering::SeqCst) == 1 {
+            let wakers = std::mem::take(&mut *self.0.wakers.lock().unwrap());
+            for (_, waker) in wakers {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl Future for Idle {
+    type Output = ();
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.tasks.counter.load(Ordering::SeqCst) == 0 {
+            return Poll::Ready(());
+        }
+        // store a single waker for each idle future.
+        match self.tasks.wakers.lock().unwrap().entry(self.key) {
+            Entry::Occupied(mut entry) => {
+                if !entry.get().will_wake(cx.waker()) {
+                    entry.insert(cx.waker().clone());
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(cx.waker().clone());
+            }
+        }
+        // check again in case the last task is complete before waker is registered.
+        if self.tasks.counter.load(Ordering::SeqCst) == 0 {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Idle {
+    #[inline]
+    fn drop(&mut self) {
+        self.tasks.wakers.lock().unwrap().remove(&self.key);
+    }
 }
 
 impl<T> Future for JoinHandle<T> {
@@ -77,7 +191,9 @@ where
 {
     #[inline]
     fn execute(&self, fut: F) {
-        self.0.spawn(Box::pin(async move {
+        let guard = self.track();
+        self.spawner.spawn(Box::pin(async move {
+            let _guard = guard;
             let _ = fut.await;
         }));
     }
@@ -86,7 +202,10 @@ where
 #[cfg(test)]
 mod tests {
     use super::{BlockingObj, Executor, FutureObj, Spawn};
-    use async_std::sync::Arc;
+    use futures::channel::oneshot::channel;
+    use futures::task::{noop_waker, Context};
+    use std::future::Future;
+    use std::pin::Pin;
 
     pub struct Exec;
 
@@ -102,13 +221,45 @@ mod tests {
 
     #[async_std::test]
     async fn spawn() {
-        let exec = Executor(Arc::new(Exec));
+        let exec = Executor::new(Exec);
         assert_eq!(1, exec.spawn(async { 1 }).await);
     }
 
     #[async_std::test]
     async fn spawn_blocking() {
-        let exec = Executor(Arc::new(Exec));
+        let exec = Executor::new(Exec);
         assert_eq!(1, exec.spawn_blocking(|| 1).await);
     }
+
+    #[async_std::test]
+    async fn idle() {
+        let exec = Executor::new(Exec);
+        exec.idle().await;
+        let (sender, recv) = channel::<()>();
+        let handle = exec.spawn(async move { recv.await.unwrap() });
+        assert_eq!(1, exec.tasks());
+        let idle = exec.idle();
+        sender.send(()).unwrap();
+        handle.await;
+        idle.await;
+        assert_eq!(0, exec.tasks());
+    }
+
+    #[async_std::test]
+    async fn idle_waker() {
+        let exec = Executor::new(Exec);
+        let (sender, recv) = channel::<()>();
+        let handle = exec.spawn(async move { recv.await.unwrap() });
+        let mut idle = exec.idle();
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        for _ in 0..10 {
+            assert!(Pin::new(&mut idle).poll(&mut cx).is_pending());
+        }
+        assert_eq!(1, exec.tasks.wakers.lock().unwrap().len());
+        drop(idle);
+        assert!(exec.tasks.wakers.lock().unwrap().is_empty());
+        sender.send(()).unwrap();
+        handle.await;
+    }
 }
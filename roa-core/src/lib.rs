@@ -14,10 +14,10 @@ mod response;
 mod state;
 
 #[doc(inline)]
-pub use app::{AddrStream, App};
+pub use app::{AddrStream, App, HttpService};
 
 #[doc(inline)]
-pub use executor::{Executor, JoinHandle, Spawn};
+pub use executor::{Executor, Idle, JoinHandle, Spawn};
 
 #[doc(inline)]
 pub use context::{Context, Variable};
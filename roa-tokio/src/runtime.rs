@@ -35,8 +35,10 @@ mod tests {
     use super::Exec;
     use roa::http::StatusCode;
     use roa::tcp::Listener;
-    use roa::App;
+    use roa::timeout::Timeout;
+    use roa::{App, Context};
     use std::error::Error;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn exec() -> Result<(), Box<dyn Error>> {
@@ -47,4 +49,20 @@ mod tests {
         assert_eq!(StatusCode::OK, resp.status());
         Ok(())
     }
+
+    #[tokio::test]
+    async fn timeout() -> Result<(), Box<dyn Error>> {
+        async fn slow(_ctx: &mut Context) -> roa::Result {
+            tokio::time::delay_for(Duration::from_secs(1)).await;
+            Ok(())
+        }
+        let app = App::with_exec((), Exec)
+            .gate(Timeout::new(Duration::from_millis(50)))
+            .end(slow);
+        let (addr, server) = app.bind("127.0.0.1:0")?;
+        tokio::spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, resp.status());
+        Ok(())
+    }
 }
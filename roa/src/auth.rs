@@ -0,0 +1,102 @@
+//! This module provides authentication middlewares `BasicAuth`, `ApiKeyAuth`
+//! and a context extension `AuthPrincipal`.
+//!
+//! Both middlewares store a typed principal in context storage if the request is authenticated,
+//! otherwise they return 401 UNAUTHORIZED.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::auth::{ApiKeyAuth, BasicAuth};
+//! use roa::preload::*;
+//! use roa::router::Router;
+//! use roa::{App, Context, MiddlewareExt};
+//! use std::collections::HashMap;
+//!
+//! #[derive(Clone)]
+//! struct Client {
+//!     name: String,
+//! }
+//!
+//! async fn check(username: String, password: String) -> roa::Result<Option<String>> {
+//!     Ok(if password == "123456" { Some(username) } else { None })
+//! }
+//!
+//! async fn admin(ctx: &mut Context) -> roa::Result {
+//!     let name = ctx.principal::<String>()?;
+//!     ctx.write(format!("Hello, {}", name));
+//!     Ok(())
+//! }
+//!
+//! async fn machine(ctx: &mut Context) -> roa::Result {
+//!     let client = ctx.principal::<Client>()?;
+//!     ctx.write(format!("Hello, {}", client.name));
+//!     Ok(())
+//! }
+//!
+//! let mut clients = HashMap::new();
+//! clients.insert("secret-key".to_string(), Client { name: "crawler".to_string() });
+//! let router = Router::new()
+//!     .on("/admin", BasicAuth::new(check).realm("admin").end(admin))
+//!     .on("/machine", ApiKeyAuth::new(clients).end(machine));
+//! let app = App::new().end(router.routes("/").unwrap());
+//! ```
+
+mod api_key;
+mod basic;
+
+pub use api_key::{ApiKeyAuth, KeyLookup, KeySource};
+pub use basic::BasicAuth;
+
+use crate::http::StatusCode;
+use crate::{Context, Result, Status};
+use std::sync::Arc;
+
+/// A private scope.
+struct AuthScope;
+
+/// Throw a internal server error.
+#[inline]
+fn auth_not_set() -> Status {
+    Status::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "middleware `BasicAuth` or `ApiKeyAuth` is not set correctly",
+        false,
+    )
+}
+
+/// A context extension.
+/// This extension must be used in downstream of middleware `BasicAuth` or `ApiKeyAuth`,
+/// otherwise you cannot get expected principal.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::{Context, Result};
+/// use roa::preload::*;
+///
+/// async fn get(ctx: &mut Context) -> Result {
+///     let username = ctx.principal::<String>()?;
+///     Ok(())
+/// }
+/// ```
+pub trait AuthPrincipal<S> {
+    /// Get the principal of authenticated request.
+    fn principal<P: 'static + Send + Sync>(&self) -> Result<Arc<P>>;
+}
+
+impl<S> AuthPrincipal<S> for Context<S> {
+    #[inline]
+    fn principal<P: 'static + Send + Sync>(&self) -> Result<Arc<P>> {
+        match self.load_scoped::<AuthScope, P>("principal") {
+            Some(principal) => Ok(principal.value()),
+            None => Err(auth_not_set()),
+        }
+    }
+}
+
+/// Store the principal.
+#[inline]
+fn store_principal<S, P: 'static + Send + Sync>(ctx: &mut Context<S>, principal: P) {
+    ctx.store_scoped(AuthScope, "principal", principal);
+}
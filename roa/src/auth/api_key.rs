@@ -0,0 +1,198 @@
+use super::store_principal;
+use crate::http::StatusCode;
+use crate::{async_trait, throw, Context, Middleware, Next, Result};
+use std::collections::HashMap;
+
+/// A source to find the api key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySource {
+    /// A request header with the name.
+    Header(String),
+
+    /// A query parameter with the name.
+    Query(String),
+}
+
+impl KeySource {
+    /// Find the api key in request.
+    #[inline]
+    fn find<S>(&self, ctx: &Context<S>) -> Option<String> {
+        let key = match self {
+            KeySource::Header(name) => ctx
+                .req
+                .headers
+                .get(name.as_str())
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string),
+            KeySource::Query(name) => {
+                url::form_urlencoded::parse(ctx.req.uri.query()?.as_bytes())
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.into_owned())
+            }
+        };
+        key.filter(|key| !key.is_empty())
+    }
+}
+
+/// A trait to look up the principal of an api key.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::auth::KeyLookup;
+/// use roa::{async_trait, Result};
+///
+/// struct Clients;
+///
+/// #[async_trait(?Send)]
+/// impl KeyLookup for Clients {
+///     type Principal = String;
+///
+///     async fn lookup(&self, key: &str) -> Result<Option<String>> {
+///         Ok(if key == "secret-key" {
+///             Some("crawler".to_string())
+///         } else {
+///             None
+///         })
+///     }
+/// }
+/// ```
+#[async_trait(?Send)]
+pub trait KeyLookup: 'static + Sync + Send {
+    /// The principal type.
+    type Principal: 'static + Send + Sync;
+
+    /// Look up the principal, return `Ok(None)` if the key is invalid.
+    async fn lookup(&self, key: &str) -> Result<Option<Self::Principal>>;
+}
+
+#[async_trait(?Send)]
+impl<P> KeyLookup for HashMap<String, P>
+where
+    P: 'static + Clone + Sync + Send,
+{
+    type Principal = P;
+
+    #[inline]
+    async fn lookup(&self, key: &str) -> Result<Option<P>> {
+        Ok(self.get(key).cloned())
+    }
+}
+
+/// A middleware to authenticate requests by api key.
+///
+/// The api key is delivered by request header "x-api-key" by default,
+/// use `ApiKeyAuth::sources` to find it in other headers or query parameters.
+///
+/// If request fails to pass authentication, return 401 UNAUTHORIZED.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::auth::{ApiKeyAuth, KeySource};
+/// use roa::App;
+/// use std::collections::HashMap;
+///
+/// let mut clients = HashMap::new();
+/// clients.insert("secret-key".to_string(), "crawler".to_string());
+/// let auth = ApiKeyAuth::new(clients).sources(vec![
+///     KeySource::Header("x-api-key".to_string()),
+///     KeySource::Query("api_key".to_string()),
+/// ]);
+/// let app = App::new().gate(auth);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ApiKeyAuth<L> {
+    lookup: L,
+    sources: Vec<KeySource>,
+}
+
+impl<L> ApiKeyAuth<L> {
+    /// Construct a middleware with a key lookup.
+    pub fn new(lookup: L) -> Self {
+        Self {
+            lookup,
+            sources: vec![KeySource::Header("x-api-key".to_string())],
+        }
+    }
+
+    /// Set sources to find the api key, the first found key is looked up.
+    pub fn sources(mut self, sources: impl IntoIterator<Item = KeySource>) -> Self {
+        self.sources = sources.into_iter().collect();
+        self
+    }
+}
+
+#[async_trait(? Send)]
+impl<'a, S, L> Middleware<'a, S> for ApiKeyAuth<L>
+where
+    L: KeyLookup,
+{
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let key = match self.sources.iter().find_map(|source| source.find(ctx)) {
+            Some(key) => key,
+            None => throw!(StatusCode::UNAUTHORIZED, "api key is missing"),
+        };
+        match self.lookup.lookup(&key).await? {
+            Some(principal) => {
+                store_principal(ctx, principal);
+                next.await
+            }
+            None => throw!(StatusCode::UNAUTHORIZED, "api key is invalid"),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{ApiKeyAuth, KeySource};
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::testing::TestClient;
+    use crate::{App, Context};
+    use std::collections::HashMap;
+
+    #[derive(Clone)]
+    struct Client {
+        name: &'static str,
+    }
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        let client = ctx.principal::<Client>()?;
+        ctx.write(client.name);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn api_key_auth() -> Result<(), Box<dyn std::error::Error>> {
+        let mut clients = HashMap::new();
+        clients.insert("secret-key".to_string(), Client { name: "crawler" });
+        let auth = ApiKeyAuth::new(clients).sources(vec![
+            KeySource::Header("x-api-key".to_string()),
+            KeySource::Query("api_key".to_string()),
+        ]);
+        let client = TestClient::new(&App::new().gate(auth).end(end));
+
+        let resp = client.get("/").send().await?;
+        assert_eq!(StatusCode::UNAUTHORIZED, resp.status);
+        assert_eq!("api key is missing", resp.text().await?);
+
+        let resp = client.get("/?api_key=wrong-key").send().await?;
+        assert_eq!(StatusCode::UNAUTHORIZED, resp.status);
+        assert_eq!("api key is invalid", resp.text().await?);
+
+        let resp = client.get("/?api_key=secret-key").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("crawler", resp.text().await?);
+
+        // header goes first
+        let resp = client
+            .get("/?api_key=secret-key")
+            .header("x-api-key", "wrong-key")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNAUTHORIZED, resp.status);
+        Ok(())
+    }
+}
@@ -38,7 +38,7 @@
 //!
 //! ```rust
 //! use roa::{Context, Result};
-//! use roa::body::{PowerBody, DispositionType::*};
+//! use roa::body::{PowerBody, Formats, DispositionType::*};
 //! use serde::{Serialize, Deserialize};
 //! use askama::Template;
 //! use async_std::fs::File;
@@ -79,34 +79,136 @@
 //!     // render html template, based on [askama](https://github.com/djc/askama).
 //!     // set "Content-Type"
 //!     ctx.render(&user)?;
+//!
+//!     // write one of representations negotiated by "Accept",
+//!     // set "Content-Type" and "Vary"
+//!     ctx.write_as(Formats::new().json(&user).html(&user))?;
 //!     Ok(())
 //! }
 //! ```
+//!
+//! ### Limit request body
+//!
+//! `PowerBody` reads the whole request body into memory, so its size is limited to 2 MiB
+//! and waiting for next chunk of body is limited to 30 seconds by default.
+//! Limits can be changed by middleware `BodyLimit`, on the whole app or on some routes.
+//!
+//! ```rust
+//! use roa::{App, Context, Result, MiddlewareExt};
+//! use roa::body::{BodyLimit, PowerBody};
+//! use roa::router::Router;
+//! use std::time::Duration;
+//!
+//! async fn upload(ctx: &mut Context) -> Result {
+//!     let data = ctx.read().await?;
+//!     Ok(())
+//! }
+//!
+//! let router = Router::new()
+//!     .on("/upload", BodyLimit::new().limit(64 * 1024 * 1024).end(upload));
+//! let app = App::new()
+//!     // 1 MB and 10 seconds for the whole app, instead of 2 MiB and 30 seconds.
+//!     .gate(BodyLimit::new().limit(1024 * 1024).timeout(Duration::from_secs(10)))
+//!     .end(router.routes("/").unwrap());
+//! ```
 
-use crate::{async_trait, http, Context, Result, State};
+use crate::{async_trait, http, throw, Context, Middleware, Next, Result, State};
 use bytes::Bytes;
-use futures::{AsyncRead, AsyncReadExt};
+use futures::future::{select, Either};
+use futures::{AsyncRead, StreamExt};
+use futures_timer::Delay;
 use lazy_static::lazy_static;
+use std::time::Duration;
 
 #[cfg(feature = "template")]
 use askama::Template;
 #[cfg(feature = "file")]
 mod file;
-#[cfg(feature = "file")]
-pub use file::DispositionType;
+mod negotiate;
 #[cfg(feature = "file")]
 use file::{write_file, Path};
+#[cfg(feature = "file")]
+pub use file::{DispositionType, ServeDir};
+pub use negotiate::Formats;
 #[cfg(any(feature = "json", feature = "urlencoded"))]
 use serde::de::DeserializeOwned;
 
-use http::{header, HeaderValue};
+use http::{header, HeaderValue, StatusCode};
 #[cfg(feature = "json")]
 use serde::Serialize;
 
+/// A private scope to store body limit in Context::storage.
+struct LimitScope;
+
+/// A private scope to store request body read ahead by middleware.
+struct BodyScope;
+
+/// Max capacity pre-allocated by `Content-Length`.
+const MAX_PREALLOCATION: usize = 64 * 1024;
+
+/// Default max size of request body.
+const DEFAULT_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Default idle timeout of reading request body.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A middleware to limit request body read by `PowerBody`.
+///
+/// The limit is enforced on the body stream, not only on `Content-Length`.
+/// Reading a body over the size limit throws 413 PAYLOAD TOO LARGE,
+/// and waiting for next chunk of body longer than the idle timeout throws 408 REQUEST TIMEOUT.
+///
+/// An inner `BodyLimit` overrides fields set by outer ones,
+/// and fields set by none of them fall back to 2 MiB and 30 seconds,
+/// as is the case without `BodyLimit`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BodyLimit {
+    limit: Option<usize>,
+    timeout: Option<Duration>,
+}
+
+impl BodyLimit {
+    /// Construct a middleware with default limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set max size of body in bytes.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Set idle timeout of reading body.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for BodyLimit {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let limit = match ctx.load_scoped::<LimitScope, BodyLimit>("limit") {
+            Some(outer) => BodyLimit {
+                limit: self.limit.or(outer.limit),
+                timeout: self.timeout.or(outer.timeout),
+            },
+            None => *self,
+        };
+        ctx.store_scoped(LimitScope, "limit", limit);
+        next.await
+    }
+}
+
 /// A context extension to read/write body more simply.
 #[async_trait]
 pub trait PowerBody {
     /// read request body as Bytes.
+    ///
+    /// Throw 413 PAYLOAD TOO LARGE or 408 REQUEST TIMEOUT if it's over `BodyLimit`,
+    /// or over 2 MiB and 30 seconds by default.
     async fn read(&mut self) -> Result<Vec<u8>>;
 
     /// read request body as "json".
@@ -142,12 +244,20 @@ pub trait PowerBody {
     where
         B: Into<Bytes>;
 
+    /// write the representation chosen by "Accept" header to response body,
+    /// set "Content-Type" and "Vary: Accept".
+    ///
+    /// Quality values are respected, and the order of `formats` breaks ties.
+    /// Throw 406 NOT ACCEPTABLE if nothing matches.
+    fn write_as(&mut self, formats: Formats<'_>) -> Result;
+
     /// write object to response body as "application/octet-stream"
     fn write_reader<B>(&mut self, reader: B)
     where
         B: 'static + AsyncRead + Unpin + Sync + Send;
 
-    /// write object to response body as extension name of file
+    /// write object to response body as extension name of file,
+    /// answer conditional GET with 304 and "Range" with 206 or 416.
     #[cfg(feature = "file")]
     #[cfg_attr(feature = "docs", doc(cfg(feature = "file")))]
     async fn write_file<P>(&mut self, path: P, typ: DispositionType) -> Result
@@ -166,18 +276,60 @@ lazy_static! {
         HeaderValue::from_static("application/octet-stream");
 }
 
+/// Message of 413 PAYLOAD TOO LARGE.
+#[inline]
+fn payload_too_large(limit: usize) -> String {
+    format!("request body is larger than {} bytes", limit)
+}
+
+/// Keep request body read by middleware, it will be returned by `PowerBody::read`.
+#[cfg(feature = "csrf")]
+pub(crate) fn keep_body<S>(ctx: &mut Context<S>, data: Vec<u8>) {
+    ctx.store_scoped(BodyScope, "body", data);
+}
+
 #[async_trait]
 impl<S: State> PowerBody for Context<S> {
     #[inline]
     async fn read(&mut self) -> Result<Vec<u8>> {
-        let size_hint = self
+        if let Some(data) = self.load_scoped::<BodyScope, Vec<u8>>("body") {
+            return Ok((*data).clone());
+        }
+        let BodyLimit { limit, timeout } = self
+            .load_scoped::<LimitScope, BodyLimit>("limit")
+            .map(|limit| *limit)
+            .unwrap_or_default();
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+        let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
+        let size_hint: Option<usize> = self
             .get(header::CONTENT_LENGTH)
             .and_then(|value| value.parse().ok());
-        let mut data = match size_hint {
-            Some(hint) => Vec::with_capacity(hint),
-            None => Vec::new(),
-        };
-        self.req.reader().read_to_end(&mut data).await?;
+        if let Some(hint) = size_hint {
+            if hint > limit {
+                throw!(StatusCode::PAYLOAD_TOO_LARGE, payload_too_large(limit))
+            }
+        }
+        let mut data = Vec::with_capacity(size_hint.unwrap_or(0).min(MAX_PREALLOCATION));
+        let mut stream = self.req.stream();
+        loop {
+            let chunk = match select(stream.next(), Delay::new(timeout)).await {
+                Either::Left((chunk, _)) => chunk,
+                Either::Right(_) => throw!(
+                    StatusCode::REQUEST_TIMEOUT,
+                    format!("no request body is received in {:?}", timeout)
+                ),
+            };
+            match chunk {
+                None => break,
+                Some(chunk) => {
+                    let chunk = chunk?;
+                    if data.len() + chunk.len() > limit {
+                        throw!(StatusCode::PAYLOAD_TOO_LARGE, payload_too_large(limit))
+                    }
+                    data.extend_from_slice(&chunk);
+                }
+            }
+        }
         Ok(data)
     }
 
@@ -188,7 +340,6 @@ impl<S: State> PowerBody for Context<S> {
         B: DeserializeOwned,
     {
         use crate::status;
-        use http::StatusCode;
         let data = self.read().await?;
         serde_json::from_slice(&data)
             .map_err(|err| status!(StatusCode::BAD_REQUEST, err))
@@ -201,7 +352,6 @@ impl<S: State> PowerBody for Context<S> {
         B: DeserializeOwned,
     {
         use crate::status;
-        use http::StatusCode;
         let data = self.read().await?;
         serde_urlencoded::from_bytes(&data)
             .map_err(|err| status!(StatusCode::BAD_REQUEST, err))
@@ -244,6 +394,11 @@ impl<S: State> PowerBody for Context<S> {
             .insert(header::CONTENT_TYPE, TEXT_PLAIN.clone());
     }
 
+    #[inline]
+    fn write_as(&mut self, formats: Formats<'_>) -> Result {
+        negotiate::write_as(self, formats)
+    }
+
     #[inline]
     fn write_reader<B>(&mut self, reader: B)
     where
@@ -267,17 +422,21 @@ impl<S: State> PowerBody for Context<S> {
 
 #[cfg(all(test, feature = "tcp"))]
 mod tests {
-    use super::PowerBody;
+    use super::{BodyLimit, Formats, PowerBody};
     use crate::http;
     use crate::tcp::Listener;
-    use crate::{App, Context};
+    use crate::testing::TestClient;
+    use crate::{App, Context, Next};
     use askama::Template;
     use async_std::fs::File;
+    use async_std::net::TcpStream;
     use async_std::task::spawn;
-    use http::header::CONTENT_TYPE;
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use http::header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, VARY};
     use http::StatusCode;
     use serde::{Deserialize, Serialize};
     use std::error::Error;
+    use std::time::Duration;
 
     #[derive(Debug, Deserialize)]
     struct UserDto {
@@ -375,6 +534,170 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(all(feature = "json", feature = "urlencoded"))]
+    #[tokio::test]
+    async fn write_as() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            ctx.write_as(Formats::new().json(&USER).form(&USER).text(&USER.name))
+        }
+        let client = TestClient::new(&App::new().end(test));
+
+        // no Accept, the first one
+        let resp = client.get("/").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("application/json", resp.headers[CONTENT_TYPE]);
+        assert_eq!("Accept", resp.headers[VARY]);
+        assert_eq!(USER, resp.json::<UserDto>().await?);
+
+        for (accept, content_type, body) in &[
+            ("text/plain", "text/plain", "Hexilee"),
+            (
+                "application/json;q=0.5, application/x-www-form-urlencoded",
+                "application/x-www-form-urlencoded",
+                "id=0&name=Hexilee",
+            ),
+            ("text/*;q=0.8, */*;q=0.1", "text/plain", "Hexilee"),
+            (
+                "text/html, */*;q=0.1",
+                "application/json",
+                r#"{"id":0,"name":"Hexilee"}"#,
+            ),
+            (
+                "application/*, application/json;q=0",
+                "application/x-www-form-urlencoded",
+                "id=0&name=Hexilee",
+            ),
+        ] {
+            let resp = client.get("/").header(ACCEPT, *accept).send().await?;
+            assert_eq!(StatusCode::OK, resp.status);
+            assert_eq!(*content_type, resp.headers[CONTENT_TYPE]);
+            assert_eq!(*body, resp.text().await?);
+        }
+
+        let resp = client.get("/").header(ACCEPT, "image/png").send().await?;
+        assert_eq!(StatusCode::NOT_ACCEPTABLE, resp.status);
+        assert_eq!("Accept", resp.headers[VARY]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn vary_once() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            ctx.write_as(Formats::new().text(&"Hexilee"))?;
+            ctx.write_as(Formats::new().text(&"Hexilee"))
+        }
+        let resp = TestClient::new(&App::new().end(test))
+            .get("/")
+            .send()
+            .await?;
+        assert_eq!(1, resp.headers.get_all(VARY).iter().count());
+
+        async fn vary(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+            ctx.resp.headers.append(VARY, "Origin, accept".parse()?);
+            next.await
+        }
+        let resp = TestClient::new(&App::new().gate(vary).end(test))
+            .get("/")
+            .send()
+            .await?;
+        let values: Vec<_> = resp.headers.get_all(VARY).iter().collect();
+        assert_eq!(vec!["Origin, accept"], values);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn body_limit() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let data = ctx.read().await?;
+            ctx.write(data);
+            Ok(())
+        }
+        let app = App::new()
+            .gate(BodyLimit::new().limit(8))
+            .gate(BodyLimit::new().timeout(Duration::from_secs(1)))
+            .end(test);
+        let client = TestClient::new(&app);
+        let resp = client.post("/").body("Hexilee").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hexilee", resp.text().await?);
+
+        // enforced on stream
+        let resp = client.post("/").body("Hello, World!").send().await?;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status);
+        assert_eq!("request body is larger than 8 bytes", resp.text().await?);
+
+        // enforced on Content-Length
+        let resp = client
+            .post("/")
+            .header(CONTENT_LENGTH, "1024")
+            .body("Hexilee")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn default_limit() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let data = ctx.read().await?;
+            ctx.write(data.len().to_string());
+            Ok(())
+        }
+        let client = TestClient::new(&App::new().end(test));
+        let resp = client
+            .post("/")
+            .body(vec![0; 2 * 1024 * 1024])
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("2097152", resp.text().await?);
+
+        let resp = client
+            .post("/")
+            .body(vec![0; 2 * 1024 * 1024 + 1])
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status);
+        assert_eq!(
+            "request body is larger than 2097152 bytes",
+            resp.text().await?
+        );
+
+        // overridden by `BodyLimit`
+        let app = App::new()
+            .gate(BodyLimit::new().limit(4 * 1024 * 1024))
+            .end(test);
+        let resp = TestClient::new(&app)
+            .post("/")
+            .body(vec![0; 2 * 1024 * 1024 + 1])
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn body_timeout() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            ctx.read().await?;
+            Ok(())
+        }
+        let app = App::new()
+            .gate(BodyLimit::new().timeout(Duration::from_millis(100)))
+            .end(test);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"POST / HTTP/1.1\r\nhost: localhost\r\ncontent-length: 16\r\n\r\nHexilee")
+            .await?;
+        let mut data = [0; 12];
+        stream.read_exact(&mut data).await?;
+        assert_eq!(b"HTTP/1.1 408", &data);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn write_octet() -> Result<(), Box<dyn Error>> {
         async fn test(ctx: &mut Context) -> crate::Result {
@@ -1,15 +1,36 @@
 mod content_disposition;
 mod help;
-use crate::{http, Context, Result, State};
+mod range;
+mod serve_dir;
+
+use crate::http::header::{self, HeaderValue};
+use crate::http::{Method, StatusCode};
+use crate::{http, throw, Context, Result, State};
 
 pub use async_std::path::Path;
 pub use content_disposition::DispositionType;
+pub use serve_dir::ServeDir;
 
 use async_std::fs::File;
 use content_disposition::ContentDisposition;
+use futures::io::{self, SeekFrom, Take};
+use futures::task::{Context as TaskContext, Poll};
+use futures::{ready, AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+use headers::{ETag, HeaderMapExt, IfModifiedSince, IfNoneMatch, IfRange, LastModified};
+use range::{parse_range, Ranges};
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
 use std::convert::TryInto;
+use std::ffi::OsStr;
+use std::hash::{BuildHasher, Hasher};
+use std::pin::Pin;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Write file to response body then set "Content-Type" and "Context-Disposition".
+///
+/// "Content-Length", "Last-Modified" and "ETag" are set by metadata of file,
+/// conditional GET by "If-None-Match" or "If-Modified-Since" is answered with 304 NOT MODIFIED,
+/// and "Range" is answered with 206 PARTIAL CONTENT or 416 RANGE NOT SATISFIABLE.
 #[inline]
 pub async fn write_file<S: State>(
     ctx: &mut Context<S>,
@@ -17,17 +38,32 @@ pub async fn write_file<S: State>(
     typ: DispositionType,
 ) -> Result {
     let path = path.as_ref();
-    ctx.resp.write_reader(File::open(path).await?);
+    serve(ctx, path, path.file_name(), typ).await
+}
 
-    if let Some(filename) = path.file_name() {
-        ctx.resp.headers.insert(
-            http::header::CONTENT_TYPE,
-            mime_guess::from_path(&filename)
-                .first_or_octet_stream()
-                .as_ref()
-                .parse()
-                .map_err(help::bug_report)?,
-        );
+/// Write file to response body, "Content-Type" and "Context-Disposition" are set by `filename`.
+async fn serve<S: State>(
+    ctx: &mut Context<S>,
+    path: &Path,
+    filename: Option<&OsStr>,
+    typ: DispositionType,
+) -> Result {
+    let file = File::open(path).await?;
+    let metadata = file.metadata().await?;
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+
+    let mut content_type = None;
+    if let Some(filename) = filename {
+        let value: HeaderValue = mime_guess::from_path(&filename)
+            .first_or_octet_stream()
+            .as_ref()
+            .parse()
+            .map_err(help::bug_report)?;
+        ctx.resp
+            .headers
+            .insert(http::header::CONTENT_TYPE, value.clone());
+        content_type = Some(value);
 
         let name = filename.to_string_lossy();
         let content_disposition = ContentDisposition::new(typ, Some(&name));
@@ -36,5 +72,384 @@ pub async fn write_file<S: State>(
             content_disposition.try_into()?,
         );
     }
+
+    let last_modified = modified.map(LastModified::from);
+    let etag = modified.and_then(|time| etag(len, time));
+    if let Some(last_modified) = last_modified {
+        ctx.resp.headers.typed_insert(last_modified);
+    }
+    if let Some(etag) = etag.clone() {
+        ctx.resp.headers.typed_insert(etag);
+    }
+    ctx.resp
+        .headers
+        .insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+
+    if *ctx.method() != Method::GET && *ctx.method() != Method::HEAD {
+        ctx.resp.headers.insert(header::CONTENT_LENGTH, len.into());
+        ctx.resp.write_reader(file);
+        return Ok(());
+    }
+
+    if !is_modified(ctx, etag.as_ref(), modified) {
+        ctx.resp.status = StatusCode::NOT_MODIFIED;
+        return Ok(());
+    }
+
+    let ranges = match ctx.req.headers.get(header::RANGE) {
+        Some(value) if is_range_fresh(ctx, etag.as_ref(), last_modified.as_ref()) => {
+            parse_range(value.to_str().unwrap_or(""), len)
+        }
+        _ => Ranges::Ignored,
+    };
+
+    match ranges {
+        Ranges::Ignored => {
+            ctx.resp.headers.insert(header::CONTENT_LENGTH, len.into());
+            ctx.resp.write_reader(file);
+        }
+        Ranges::Unsatisfiable => {
+            ctx.resp.headers.insert(
+                header::CONTENT_RANGE,
+                format!("bytes */{}", len)
+                    .parse()
+                    .map_err(help::bug_report)?,
+            );
+            throw!(
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                format!("range is not satisfiable, file has {} bytes", len)
+            );
+        }
+        Ranges::Satisfiable(ranges) if ranges.len() == 1 => {
+            let (start, end) = ranges[0];
+            ctx.resp.status = StatusCode::PARTIAL_CONTENT;
+            ctx.resp.headers.insert(
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", start, end, len)
+                    .parse()
+                    .map_err(help::bug_report)?,
+            );
+            ctx.resp
+                .headers
+                .insert(header::CONTENT_LENGTH, (end - start + 1).into());
+            ctx.resp.write_reader(slice(file, start, end).await?);
+        }
+        Ranges::Satisfiable(ranges) => {
+            let boundary = boundary();
+            let content_type =
+                content_type.as_ref().and_then(|value| value.to_str().ok());
+            let mut parts = VecDeque::with_capacity(ranges.len() + 1);
+            let mut content_length = 0;
+            for (start, end) in ranges {
+                let mut head = format!("\r\n--{}\r\n", boundary);
+                if let Some(content_type) = content_type {
+                    head.push_str(&format!("Content-Type: {}\r\n", content_type));
+                }
+                head.push_str(&format!(
+                    "Content-Range: bytes {}-{}/{}\r\n\r\n",
+                    start, end, len
+                ));
+                content_length += head.len() as u64 + end - start + 1;
+                parts.push_back(Part::new(head, start, end - start + 1));
+            }
+            let closing = format!("\r\n--{}--\r\n", boundary);
+            content_length += closing.len() as u64;
+            parts.push_back(Part::new(closing, 0, 0));
+            ctx.resp.write_reader(Multipart { file, parts });
+
+            ctx.resp.status = StatusCode::PARTIAL_CONTENT;
+            ctx.resp.headers.insert(
+                header::CONTENT_TYPE,
+                format!("multipart/byteranges; boundary={}", boundary)
+                    .parse()
+                    .map_err(help::bug_report)?,
+            );
+            ctx.resp
+                .headers
+                .insert(header::CONTENT_LENGTH, content_length.into());
+        }
+    }
     Ok(())
 }
+
+/// Strong entity tag made up of length and modified time of file.
+fn etag(len: u64, modified: SystemTime) -> Option<ETag> {
+    let mtime = modified.duration_since(UNIX_EPOCH).ok()?;
+    format!("\"{:x}-{:x}\"", mtime.as_secs(), len).parse().ok()
+}
+
+/// Check "If-None-Match" or "If-Modified-Since", "If-None-Match" takes precedence.
+fn is_modified<S>(
+    ctx: &Context<S>,
+    etag: Option<&ETag>,
+    modified: Option<SystemTime>,
+) -> bool {
+    if let Some(if_none_match) = ctx.req.headers.typed_get::<IfNoneMatch>() {
+        return match etag {
+            Some(etag) => if_none_match.precondition_passes(etag),
+            None => true,
+        };
+    }
+    match (ctx.req.headers.typed_get::<IfModifiedSince>(), modified) {
+        (Some(since), Some(modified)) => since.is_modified(modified),
+        _ => true,
+    }
+}
+
+/// Check "If-Range", the range is ignored if file is modified.
+fn is_range_fresh<S>(
+    ctx: &Context<S>,
+    etag: Option<&ETag>,
+    last_modified: Option<&LastModified>,
+) -> bool {
+    match ctx.req.headers.typed_get::<IfRange>() {
+        Some(if_range) => !if_range.is_modified(etag, last_modified),
+        None => true,
+    }
+}
+
+/// Read bytes of file in inclusive range.
+async fn slice(mut file: File, start: u64, end: u64) -> Result<Take<File>> {
+    file.seek(SeekFrom::Start(start)).await?;
+    Ok(file.take(end - start + 1))
+}
+
+/// A part of "multipart/byteranges", the head followed by `len` bytes of file from `start`.
+struct Part {
+    head: Vec<u8>,
+    written: usize,
+    start: u64,
+    len: u64,
+    sought: bool,
+}
+
+impl Part {
+    fn new(head: String, start: u64, len: u64) -> Self {
+        Self {
+            head: head.into_bytes(),
+            written: 0,
+            start,
+            len,
+            sought: false,
+        }
+    }
+}
+
+/// Body of "multipart/byteranges", reading all parts by one file handle.
+struct Multipart {
+    file: File,
+    parts: VecDeque<Part>,
+}
+
+impl AsyncRead for Multipart {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let Multipart { file, parts } = self.get_mut();
+        loop {
+            let part = match parts.front_mut() {
+                Some(part) => part,
+                None => return Poll::Ready(Ok(0)),
+            };
+            if part.written < part.head.len() {
+                let head = &part.head[part.written..];
+                let size = head.len().min(buf.len());
+                buf[..size].copy_from_slice(&head[..size]);
+                part.written += size;
+                return Poll::Ready(Ok(size));
+            }
+            if part.len == 0 {
+                parts.pop_front();
+                continue;
+            }
+            if !part.sought {
+                ready!(Pin::new(&mut *file).poll_seek(cx, SeekFrom::Start(part.start)))?;
+                part.sought = true;
+            }
+            let size = (part.len.min(buf.len() as u64)) as usize;
+            let size = ready!(Pin::new(&mut *file).poll_read(cx, &mut buf[..size]))?;
+            if size == 0 {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            part.len -= size as u64;
+            return Poll::Ready(Ok(size));
+        }
+    }
+}
+
+/// Random boundary of "multipart/byteranges".
+fn boundary() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos())
+            .unwrap_or_default(),
+    );
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use crate::body::{DispositionType::*, PowerBody};
+    use crate::http::header::{
+        ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+        IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE,
+    };
+    use crate::http::StatusCode;
+    use crate::testing::TestClient;
+    use crate::{App, Context};
+
+    async fn author(ctx: &mut Context) -> crate::Result {
+        ctx.write_file("../assets/author.txt", Inline).await
+    }
+
+    #[tokio::test]
+    async fn metadata() -> Result<(), Box<dyn std::error::Error>> {
+        let client = TestClient::new(&App::new().end(author));
+        let resp = client.get("/").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("7", resp.headers[CONTENT_LENGTH]);
+        assert_eq!("bytes", resp.headers[ACCEPT_RANGES]);
+        let etag = resp.headers[ETAG].clone();
+        let last_modified = resp.headers[LAST_MODIFIED].clone();
+        assert_eq!("Hexilee", resp.text().await?);
+
+        let resp = client
+            .get("/")
+            .header(IF_NONE_MATCH, etag.clone())
+            .send()
+            .await?;
+        assert_eq!(StatusCode::NOT_MODIFIED, resp.status);
+        assert_eq!("", resp.text().await?);
+
+        let resp = client
+            .get("/")
+            .header(IF_MODIFIED_SINCE, last_modified.clone())
+            .send()
+            .await?;
+        assert_eq!(StatusCode::NOT_MODIFIED, resp.status);
+
+        // If-None-Match takes precedence.
+        let resp = client
+            .get("/")
+            .header(IF_NONE_MATCH, "\"other\"")
+            .header(IF_MODIFIED_SINCE, last_modified)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hexilee", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn range() -> Result<(), Box<dyn std::error::Error>> {
+        let client = TestClient::new(&App::new().end(author));
+        let resp = client.get("/").header(RANGE, "bytes=0-2").send().await?;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status);
+        assert_eq!("bytes 0-2/7", resp.headers[CONTENT_RANGE]);
+        assert_eq!("3", resp.headers[CONTENT_LENGTH]);
+        assert_eq!("Hex", resp.text().await?);
+
+        let resp = client.get("/").header(RANGE, "bytes=-4").send().await?;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status);
+        assert_eq!("bytes 3-6/7", resp.headers[CONTENT_RANGE]);
+        assert_eq!("ilee", resp.text().await?);
+
+        let resp = client.get("/").header(RANGE, "bytes=7-").send().await?;
+        assert_eq!(StatusCode::RANGE_NOT_SATISFIABLE, resp.status);
+        assert_eq!("bytes */7", resp.headers[CONTENT_RANGE]);
+
+        // invalid range is ignored.
+        let resp = client.get("/").header(RANGE, "bytes=3-1").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hexilee", resp.text().await?);
+
+        // outdated If-Range
+        let resp = client
+            .get("/")
+            .header(RANGE, "bytes=0-2")
+            .header(IF_RANGE, "\"outdated\"")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hexilee", resp.text().await?);
+
+        let etag = client.get("/").send().await?.headers[ETAG].clone();
+        let resp = client
+            .get("/")
+            .header(RANGE, "bytes=0-2")
+            .header(IF_RANGE, etag)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status);
+        assert_eq!("Hex", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn multipart_range() -> Result<(), Box<dyn std::error::Error>> {
+        let client = TestClient::new(&App::new().end(author));
+        let resp = client
+            .get("/")
+            .header(RANGE, "bytes=0-2, 5-")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status);
+        let content_type = resp.headers[CONTENT_TYPE].to_str()?.to_string();
+        let boundary = content_type
+            .trim_start_matches("multipart/byteranges; boundary=")
+            .to_string();
+        let content_length: usize = resp.headers[CONTENT_LENGTH].to_str()?.parse()?;
+        let body = resp.text().await?;
+        assert_eq!(content_length, body.len());
+        assert_eq!(
+            format!(
+                "\r\n--{b}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-2/7\r\n\r\nHex\
+                 \r\n--{b}\r\nContent-Type: text/plain\r\nContent-Range: bytes 5-6/7\r\n\r\nee\
+                 \r\n--{b}--\r\n",
+                b = boundary
+            ),
+            body
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn overlapping_ranges() -> Result<(), Box<dyn std::error::Error>> {
+        let client = TestClient::new(&App::new().end(author));
+        let ranges = format!("bytes={}", vec!["0-2, 1-3, -1"; 100].join(","));
+        let resp = client.get("/").header(RANGE, ranges).send().await?;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status);
+        let content_type = resp.headers[CONTENT_TYPE].to_str()?.to_string();
+        let boundary = content_type
+            .trim_start_matches("multipart/byteranges; boundary=")
+            .to_string();
+        assert_eq!(
+            format!(
+                "\r\n--{b}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-3/7\r\n\r\nHexi\
+                 \r\n--{b}\r\nContent-Type: text/plain\r\nContent-Range: bytes 6-6/7\r\n\r\ne\
+                 \r\n--{b}--\r\n",
+                b = boundary
+            ),
+            resp.text().await?
+        );
+
+        // adjacent ranges are merged into a single range.
+        let ranges = (0..7)
+            .map(|index| format!("{}-{}", index, index))
+            .collect::<Vec<_>>()
+            .join(",");
+        let resp = client
+            .get("/")
+            .header(RANGE, format!("bytes={}", ranges))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status);
+        assert_eq!("bytes 0-6/7", resp.headers[CONTENT_RANGE]);
+        assert_eq!("Hexilee", resp.text().await?);
+        Ok(())
+    }
+}
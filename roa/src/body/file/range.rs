@@ -0,0 +1,136 @@
+/// Max number of ranges after merging, the header is ignored if there are more.
+const MAX_RANGES: usize = 32;
+
+/// Result of parsing "Range" header against a file.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Ranges {
+    /// Header is invalid or in unknown unit, serve the whole file.
+    Ignored,
+
+    /// No range is satisfiable.
+    Unsatisfiable,
+
+    /// Satisfiable ranges, as inclusive `(start, end)`,
+    /// sorted and without overlapping or adjacent ones.
+    Satisfiable(Vec<(u64, u64)>),
+}
+
+/// Parse "Range" header like `bytes=0-99, 200-, -500` against a file of `len` bytes.
+///
+/// Overlapping or adjacent ranges are merged,
+/// and the header is ignored if there are still more than `MAX_RANGES` ranges.
+pub fn parse_range(value: &str, len: u64) -> Ranges {
+    let value = value.trim();
+    let specs = match value.find('=') {
+        Some(index) if value[..index].trim().eq_ignore_ascii_case("bytes") => {
+            &value[index + 1..]
+        }
+        _ => return Ranges::Ignored,
+    };
+    let mut ranges = Vec::new();
+    for spec in specs
+        .split(',')
+        .map(str::trim)
+        .filter(|spec| !spec.is_empty())
+    {
+        let index = match spec.find('-') {
+            Some(index) => index,
+            None => return Ranges::Ignored,
+        };
+        let (start, end) = (spec[..index].trim(), spec[index + 1..].trim());
+        let range = if start.is_empty() {
+            // suffix range, the last N bytes.
+            match end.parse::<u64>() {
+                Ok(0) => None,
+                Ok(suffix) if len > 0 => Some((len.saturating_sub(suffix), len - 1)),
+                Ok(_) => None,
+                Err(_) => return Ranges::Ignored,
+            }
+        } else {
+            let start = match start.parse::<u64>() {
+                Ok(start) => start,
+                Err(_) => return Ranges::Ignored,
+            };
+            let end = if end.is_empty() {
+                len.saturating_sub(1)
+            } else {
+                match end.parse::<u64>() {
+                    Ok(end) if end >= start => end,
+                    _ => return Ranges::Ignored,
+                }
+            };
+            if start < len {
+                Some((start, end.min(len - 1)))
+            } else {
+                None
+            }
+        };
+        ranges.extend(range);
+    }
+
+    if ranges.is_empty() {
+        return Ranges::Unsatisfiable;
+    }
+    let ranges = merge(ranges);
+    if ranges.len() > MAX_RANGES {
+        Ranges::Ignored
+    } else {
+        Ranges::Satisfiable(ranges)
+    }
+}
+
+/// Sort ranges and merge overlapping or adjacent ones.
+fn merge(mut ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_range, Ranges};
+    use test_case::test_case;
+
+    #[test_case("bytes=0-3", 7 => Ranges::Satisfiable(vec![(0, 3)]); "head")]
+    #[test_case("bytes=3-", 7 => Ranges::Satisfiable(vec![(3, 6)]); "open")]
+    #[test_case("bytes=-3", 7 => Ranges::Satisfiable(vec![(4, 6)]); "suffix")]
+    #[test_case("bytes=-10", 7 => Ranges::Satisfiable(vec![(0, 6)]); "long suffix")]
+    #[test_case("bytes=2-100", 7 => Ranges::Satisfiable(vec![(2, 6)]); "long end")]
+    #[test_case("Bytes = 0-0, 2-3", 7 => Ranges::Satisfiable(vec![(0, 0), (2, 3)]); "multi")]
+    #[test_case("bytes=4-5, 0-1", 7 => Ranges::Satisfiable(vec![(0, 1), (4, 5)]); "sorted")]
+    #[test_case("bytes=0-2, 1-3, 4-4, -1", 7 => Ranges::Satisfiable(vec![(0, 4), (6, 6)]); "merged")]
+    #[test_case("bytes=0-0, 7-", 7 => Ranges::Satisfiable(vec![(0, 0)]); "partial")]
+    #[test_case("bytes=7-", 7 => Ranges::Unsatisfiable; "out of range")]
+    #[test_case("bytes=-0", 7 => Ranges::Unsatisfiable; "empty suffix")]
+    #[test_case("bytes=0-", 0 => Ranges::Unsatisfiable; "empty file")]
+    #[test_case("bytes=3-1", 7 => Ranges::Ignored; "reversed")]
+    #[test_case("bytes=a-1", 7 => Ranges::Ignored; "not a number")]
+    #[test_case("bytes=1", 7 => Ranges::Ignored; "no dash")]
+    #[test_case("items=0-1", 7 => Ranges::Ignored; "unknown unit")]
+    fn parse(value: &str, len: u64) -> Ranges {
+        parse_range(value, len)
+    }
+
+    #[test]
+    fn too_many_ranges() {
+        let overlapping = vec!["0-3"; 1000].join(",");
+        assert_eq!(
+            Ranges::Satisfiable(vec![(0, 3)]),
+            parse_range(&format!("bytes={}", overlapping), 7)
+        );
+        let disjoint = (0..100)
+            .map(|index| format!("{}-{}", index * 2, index * 2))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(
+            Ranges::Ignored,
+            parse_range(&format!("bytes={}", disjoint), 1000)
+        );
+    }
+}
@@ -0,0 +1,420 @@
+use super::{serve, DispositionType};
+use crate::http::header::{
+    HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE, VARY,
+};
+use crate::http::StatusCode;
+use crate::{async_trait, throw, Context, Endpoint, Result, State};
+use async_std::path::{Path, PathBuf};
+use bytesize::ByteSize;
+use futures::StreamExt;
+use headers::{Header, LastModified};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use std::time::SystemTime;
+
+#[cfg(feature = "router")]
+use crate::router::RouterParam;
+
+/// Characters to be encoded in a link of directory listing.
+const LINK: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Name of index file.
+const INDEX: &str = "index.html";
+
+/// An endpoint to serve files in a directory.
+///
+/// The file path is taken from router variable `path`, like `/*{path}`,
+/// the root directory is served if there is no such variable.
+/// Without feature "router", the whole uri path is taken.
+/// Requests escaping from the root directory are rejected with 400 BAD REQUEST.
+///
+/// When a directory is requested, "index.html" in it is served if present,
+/// otherwise a listing is rendered if enabled, or 404 NOT FOUND is thrown.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::body::ServeDir;
+/// use roa::router::Router;
+/// use roa::App;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let assets = ServeDir::new("./assets").listing(true).precompressed(true);
+/// let router = Router::new()
+///     .get("/assets", assets.clone())
+///     .get("/assets/*{path}", assets);
+/// let app = App::new().end(router.routes("/")?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServeDir {
+    root: PathBuf,
+    listing: bool,
+    precompressed: bool,
+}
+
+/// An entry of directory listing.
+struct Entry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl ServeDir {
+    /// Construct an endpoint serving files in `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            listing: false,
+            precompressed: false,
+        }
+    }
+
+    /// Render a listing for directories without "index.html", disabled by default.
+    pub fn listing(mut self, listing: bool) -> Self {
+        self.listing = listing;
+        self
+    }
+
+    /// Serve precompressed ".br" or ".gz" sibling of a file if the client accepts it,
+    /// disabled by default.
+    pub fn precompressed(mut self, precompressed: bool) -> Self {
+        self.precompressed = precompressed;
+        self
+    }
+
+    /// Serve a file, or its precompressed sibling.
+    async fn serve_file<S: State>(&self, ctx: &mut Context<S>, path: &Path) -> Result {
+        if self.precompressed {
+            ctx.resp
+                .headers
+                .append(VARY, HeaderValue::from_static("Accept-Encoding"));
+            for (encoding, extension) in &[("br", "br"), ("gzip", "gz")] {
+                if !accepts(ctx, encoding) {
+                    continue;
+                }
+                let mut sibling = path.as_os_str().to_owned();
+                sibling.push(".");
+                sibling.push(extension);
+                let sibling = PathBuf::from(sibling);
+                if sibling.is_file().await {
+                    ctx.resp
+                        .headers
+                        .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+                    return serve(
+                        ctx,
+                        &sibling,
+                        path.file_name(),
+                        DispositionType::Inline,
+                    )
+                    .await;
+                }
+            }
+        }
+        serve(ctx, path, path.file_name(), DispositionType::Inline).await
+    }
+
+    /// Render a listing of directory.
+    async fn render_listing<S>(&self, ctx: &mut Context<S>, dir: &Path) -> Result {
+        let mut entries = Vec::new();
+        let mut dir_entries = dir.read_dir().await?;
+        while let Some(entry) = dir_entries.next().await {
+            let entry = entry?;
+            let metadata = entry.metadata().await?;
+            entries.push(Entry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                is_dir: metadata.is_dir(),
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+            });
+        }
+        entries
+            .sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+        let base = ctx.uri().path().trim_end_matches('/').to_string();
+        let title = escape(&format!("Index of {}/", base));
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n<hr>\n\
+             <table>\n<thead>\n<tr><th>Name</th><th>Size</th><th>Modified</th></tr>\n\
+             </thead>\n<tbody>\n",
+            title = title
+        );
+        for entry in entries {
+            let slash = if entry.is_dir { "/" } else { "" };
+            let size = if entry.is_dir {
+                "-".to_string()
+            } else {
+                ByteSize(entry.size).to_string()
+            };
+            html.push_str(&format!(
+                "<tr><td><a href=\"{base}/{link}{slash}\">{name}{slash}</a></td>\
+                 <td>{size}</td><td>{modified}</td></tr>\n",
+                base = escape(&base),
+                link = utf8_percent_encode(&entry.name, LINK),
+                name = escape(&entry.name),
+                slash = slash,
+                size = size,
+                modified = entry.modified.map(http_date).unwrap_or_default(),
+            ));
+        }
+        html.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+        ctx.resp.write(html);
+        ctx.resp.headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/html; charset=utf-8"),
+        );
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S: State> Endpoint<'a, S> for ServeDir {
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        let path = match resolve(&self.root, &request_path(ctx)) {
+            Some(path) => path,
+            None => throw!(StatusCode::BAD_REQUEST, "invalid path"),
+        };
+        if path.is_file().await {
+            return self.serve_file(ctx, &path).await;
+        }
+        if !path.is_dir().await {
+            throw!(StatusCode::NOT_FOUND, "path not found")
+        }
+        let index = path.join(INDEX);
+        if index.is_file().await {
+            self.serve_file(ctx, &index).await
+        } else if self.listing {
+            self.render_listing(ctx, &path).await
+        } else {
+            throw!(StatusCode::NOT_FOUND, "path not found")
+        }
+    }
+}
+
+/// Get the requested path, relative to root directory.
+fn request_path<S>(ctx: &Context<S>) -> String {
+    #[cfg(feature = "router")]
+    {
+        ctx.param("path")
+            .map(|path| path.to_string())
+            .unwrap_or_default()
+    }
+    #[cfg(not(feature = "router"))]
+    {
+        percent_encoding::percent_decode_str(ctx.uri().path())
+            .decode_utf8_lossy()
+            .to_string()
+    }
+}
+
+/// Join path to root, return `None` if it tries to escape from root.
+fn resolve(root: &Path, path: &str) -> Option<PathBuf> {
+    let mut resolved = root.to_path_buf();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => return None,
+            _ if segment.contains('\\') || segment.contains('\0') => return None,
+            // like `C:` on windows
+            _ if cfg!(windows) && segment.contains(':') => return None,
+            _ => resolved.push(segment),
+        }
+    }
+    Some(resolved)
+}
+
+/// Check whether "Accept-Encoding" allows an encoding.
+fn accepts<S>(ctx: &Context<S>, encoding: &str) -> bool {
+    ctx.req
+        .headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|coding| {
+            let mut parts = coding.split(';').map(str::trim);
+            let name = parts.next().unwrap_or("");
+            let quality = parts
+                .filter_map(|param| {
+                    let mut pair = param.splitn(2, '=').map(str::trim);
+                    match (pair.next(), pair.next()) {
+                        (Some("q"), Some(value)) => value.parse::<f32>().ok(),
+                        _ => None,
+                    }
+                })
+                .next()
+                .unwrap_or(1.0);
+            quality > 0.0 && (name.eq_ignore_ascii_case(encoding) || name == "*")
+        })
+}
+
+/// Escape text in html.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Format time as HTTP-date.
+fn http_date(time: SystemTime) -> String {
+    let mut values = Vec::new();
+    LastModified::from(time).encode(&mut values);
+    values
+        .first()
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(all(test, feature = "tcp", feature = "router"))]
+mod tests {
+    use super::{escape, resolve, ServeDir};
+    use crate::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE};
+    use crate::http::StatusCode;
+    use crate::router::Router;
+    use crate::testing::TestClient;
+    use crate::App;
+    use async_std::fs;
+    use async_std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// A unique temporary directory, removed on drop.
+    struct TempDir(std::path::PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos())
+                .unwrap_or(0);
+            Self(std::env::temp_dir().join(format!(
+                "roa-serve-dir-{}-{}",
+                std::process::id(),
+                nanos
+            )))
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn resolve_path() {
+        let root = PathBuf::from("assets");
+        assert_eq!(
+            Some(root.join("css/table.css")),
+            resolve(&root, "css/table.css")
+        );
+        assert_eq!(Some(root.join("css")), resolve(&root, "/./css/"));
+        assert_eq!(Some(root.clone()), resolve(&root, ""));
+        assert_eq!(None, resolve(&root, "../Cargo.toml"));
+        assert_eq!(None, resolve(&root, "css/../../Cargo.toml"));
+        assert_eq!(None, resolve(&root, "..\\Cargo.toml"));
+    }
+
+    #[test]
+    fn escape_html() {
+        assert_eq!("&lt;a href=&quot;&amp;&#x27;&gt;", escape("<a href=\"&'>"));
+    }
+
+    #[tokio::test]
+    async fn serve_dir() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new();
+        let root = &dir.0;
+        fs::create_dir_all(root.join("site")).await?;
+        fs::create_dir_all(root.join("docs")).await?;
+        fs::write(root.join("site/index.html"), "<h1>Hello</h1>").await?;
+        fs::write(root.join("docs/a <b>.txt"), "a").await?;
+        fs::write(root.join("docs/c.txt"), "c").await?;
+        fs::write(root.join("docs/c.txt.gz"), "gzip c").await?;
+        fs::create_dir_all(root.join("docs/it's")).await?;
+        fs::write(root.join("docs/it's/d.txt"), "d").await?;
+        fs::write(root.join("secret.txt"), "secret").await?;
+
+        let site = ServeDir::new(root.join("site"));
+        let docs = ServeDir::new(root.join("docs"))
+            .listing(true)
+            .precompressed(true);
+        let router = Router::new()
+            .get("/site", site.clone())
+            .get("/site/*{path}", site)
+            .get("/docs", docs.clone())
+            .get("/docs/*{path}", docs);
+        let client = TestClient::new(&App::new().end(router.routes("/")?));
+
+        let resp = client.get("/site/").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("text/html", resp.headers[CONTENT_TYPE]);
+        assert_eq!("<h1>Hello</h1>", resp.text().await?);
+
+        let resp = client.get("/site/index.html").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+
+        let resp = client.get("/site/missing.html").send().await?;
+        assert_eq!(StatusCode::NOT_FOUND, resp.status);
+
+        let resp = client.get("/site/%2E%2E/secret.txt").send().await?;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+
+        let resp = client.get("/docs").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        let listing = resp.text().await?;
+        assert!(
+            listing.contains(r#"<a href="/docs/a%20%3Cb%3E.txt">a &lt;b&gt;.txt</a>"#)
+        );
+        assert!(listing.contains(r#"<a href="/docs/c.txt">c.txt</a>"#));
+
+        // base path is escaped.
+        let resp = client.get("/docs/it's/").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        let listing = resp.text().await?;
+        assert!(listing.contains(r#"<a href="/docs/it&#x27;s/d.txt">d.txt</a>"#));
+
+        let resp = client.get("/docs/c.txt").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("c", resp.text().await?);
+
+        let resp = client
+            .get("/docs/c.txt")
+            .header(ACCEPT_ENCODING, "br, gzip")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("gzip", resp.headers[CONTENT_ENCODING]);
+        assert_eq!("text/plain", resp.headers[CONTENT_TYPE]);
+        assert_eq!("gzip c", resp.text().await?);
+
+        let resp = client
+            .get("/docs/c.txt")
+            .header(ACCEPT_ENCODING, "gzip;q=0")
+            .send()
+            .await?;
+        assert_eq!("c", resp.text().await?);
+        Ok(())
+    }
+}
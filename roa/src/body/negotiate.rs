@@ -0,0 +1,230 @@
+use crate::http::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, VARY};
+use crate::http::StatusCode;
+use crate::{throw, Context, Result};
+use bytes::Bytes;
+use std::fmt::Display;
+
+#[cfg(feature = "template")]
+use askama::Template;
+
+#[cfg(any(feature = "json", feature = "urlencoded"))]
+use serde::Serialize;
+
+/// A lazy serializer of one representation.
+type Serializer<'a> = Box<dyn 'a + FnOnce() -> Result<Bytes>>;
+
+/// Candidate representations of a value, negotiated by `PowerBody::write_as`.
+///
+/// The order of registration is the preference of server,
+/// it's used when the client accepts several representations with the same quality.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::{Context, Result};
+/// use roa::body::{Formats, PowerBody};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     id: u64,
+///     name: String,
+/// }
+///
+/// async fn get(ctx: &mut Context) -> Result {
+///     let user = User { id: 0, name: "Hexilee".to_string() };
+///     ctx.write_as(Formats::new().json(&user).form(&user).text(&user.name))
+/// }
+/// ```
+pub struct Formats<'a> {
+    candidates: Vec<(HeaderValue, Serializer<'a>)>,
+}
+
+/// A media range in `Accept` header, like `text/*;q=0.8`.
+struct MediaRange<'a> {
+    typ: &'a str,
+    subtype: &'a str,
+    quality: f32,
+}
+
+impl<'a> Formats<'a> {
+    /// Construct empty candidates.
+    pub fn new() -> Self {
+        Self {
+            candidates: Vec::new(),
+        }
+    }
+
+    /// Add a candidate with content type and a lazy serializer.
+    fn push(
+        mut self,
+        content_type: &'static str,
+        serializer: impl 'a + FnOnce() -> Result<Bytes>,
+    ) -> Self {
+        self.candidates
+            .push((HeaderValue::from_static(content_type), Box::new(serializer)));
+        self
+    }
+
+    /// Represent data as "application/json".
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+    pub fn json<B>(self, data: &'a B) -> Self
+    where
+        B: Serialize,
+    {
+        self.push("application/json", move || {
+            Ok(serde_json::to_vec(data)?.into())
+        })
+    }
+
+    /// Represent data as "application/x-www-form-urlencoded".
+    #[cfg(feature = "urlencoded")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "urlencoded")))]
+    pub fn form<B>(self, data: &'a B) -> Self
+    where
+        B: Serialize,
+    {
+        self.push("application/x-www-form-urlencoded", move || {
+            Ok(serde_urlencoded::to_string(data)?.into())
+        })
+    }
+
+    /// Represent data as "text/plain".
+    pub fn text<B>(self, data: &'a B) -> Self
+    where
+        B: Display,
+    {
+        self.push("text/plain", move || Ok(data.to_string().into()))
+    }
+
+    /// Render data as "text/html; charset=utf-8".
+    #[cfg(feature = "template")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "template")))]
+    pub fn html<B>(self, data: &'a B) -> Self
+    where
+        B: Template,
+    {
+        self.push("text/html; charset=utf-8", move || {
+            Ok(data.render()?.into())
+        })
+    }
+}
+
+impl Default for Formats<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parse media ranges of `Accept` header, invalid ones are ignored.
+fn parse_accept(value: &str) -> Vec<MediaRange<'_>> {
+    value
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let mut media = parts.next()?.splitn(2, '/');
+            let typ = media.next().filter(|typ| !typ.is_empty())?;
+            let subtype = media.next().filter(|subtype| !subtype.is_empty())?;
+            let quality = parts
+                .filter_map(|param| {
+                    let mut pair = param.splitn(2, '=').map(str::trim);
+                    match (pair.next(), pair.next()) {
+                        (Some(key), Some(value)) if key.eq_ignore_ascii_case("q") => {
+                            value.parse().ok()
+                        }
+                        _ => None,
+                    }
+                })
+                .next()
+                .unwrap_or(1.0);
+            Some(MediaRange {
+                typ,
+                subtype,
+                quality,
+            })
+        })
+        .collect()
+}
+
+/// Quality of a content type, given by the most specific media range matching it.
+fn quality(ranges: &[MediaRange<'_>], content_type: &str) -> f32 {
+    let essence = content_type.split(';').next().unwrap_or("");
+    let mut media = essence.splitn(2, '/');
+    let typ = media.next().unwrap_or("");
+    let subtype = media.next().unwrap_or("");
+    ranges
+        .iter()
+        .filter_map(|range| {
+            let specificity = match (range.typ, range.subtype) {
+                ("*", "*") => 0,
+                (t, "*") if t.eq_ignore_ascii_case(typ) => 1,
+                (t, s)
+                    if t.eq_ignore_ascii_case(typ)
+                        && s.eq_ignore_ascii_case(subtype) =>
+                {
+                    2
+                }
+                _ => return None,
+            };
+            Some((specificity, range.quality))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map(|(_, quality)| quality)
+        .unwrap_or(0.0)
+}
+
+/// Whether "Vary" header contains "Accept" or "*".
+fn varies_by_accept(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|name| name == "*" || name.eq_ignore_ascii_case("accept"))
+}
+
+/// Pick a representation by `Accept` header and write it to response body.
+pub fn write_as<S>(ctx: &mut Context<S>, formats: Formats<'_>) -> Result {
+    if !varies_by_accept(&ctx.resp.headers) {
+        ctx.resp
+            .headers
+            .append(VARY, HeaderValue::from_static("Accept"));
+    }
+    let accept = ctx
+        .req
+        .headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<&str>>()
+        .join(",");
+    let mut candidates = formats.candidates.into_iter();
+    let chosen = if accept.trim().is_empty() {
+        candidates.next()
+    } else {
+        let ranges = parse_accept(&accept);
+        let mut chosen = None;
+        let mut best = 0.0;
+        for (content_type, serializer) in candidates {
+            let quality = quality(&ranges, content_type.to_str().unwrap_or(""));
+            if quality > best {
+                best = quality;
+                chosen = Some((content_type, serializer));
+            }
+        }
+        chosen
+    };
+    match chosen {
+        Some((content_type, serializer)) => {
+            ctx.resp.write(serializer()?);
+            ctx.resp.headers.insert(CONTENT_TYPE, content_type);
+            Ok(())
+        }
+        None => throw!(
+            StatusCode::NOT_ACCEPTABLE,
+            format!("no acceptable representation for `{}`", accept)
+        ),
+    }
+}
@@ -23,7 +23,8 @@
 
 pub use async_compression::Level;
 
-use crate::http::{header::CONTENT_ENCODING, StatusCode};
+use crate::http::header::{HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, ETAG};
+use crate::http::StatusCode;
 use crate::{async_trait, Context, Middleware, Next, Result, Status};
 use accept_encoding::{parse, Encoding};
 use async_compression::stream::{BrotliEncoder, GzipEncoder, ZlibEncoder, ZstdEncoder};
@@ -45,6 +46,14 @@ impl<'a, S> Middleware<'a, S> for Compress {
     #[inline]
     async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
         next.await?;
+        // ranges and validators refer to the identity body,
+        // and precompressed body should not be compressed again.
+        if ctx.resp.status == StatusCode::PARTIAL_CONTENT
+            || ctx.resp.status == StatusCode::NOT_MODIFIED
+            || ctx.resp.headers.contains_key(CONTENT_ENCODING)
+        {
+            return Ok(());
+        }
         let level = self.0;
         let best_encoding = parse(&ctx.req.headers)
             .map_err(|err| Status::new(StatusCode::BAD_REQUEST, err, true))?;
@@ -75,11 +84,29 @@ impl<'a, S> Middleware<'a, S> for Compress {
                 Encoding::Identity.to_header_value()
             }
         };
+        if content_encoding != Encoding::Identity.to_header_value() {
+            // length is unknown and the body is no longer byte-for-byte the same.
+            ctx.resp.headers.remove(CONTENT_LENGTH);
+            weaken_etag(ctx)?;
+        }
         ctx.resp.headers.append(CONTENT_ENCODING, content_encoding);
         Ok(())
     }
 }
 
+/// Turn a strong "ETag" into a weak one.
+fn weaken_etag<S>(ctx: &mut Context<S>) -> Result {
+    if let Some(etag) = ctx.resp.headers.get(ETAG) {
+        if !etag.as_bytes().starts_with(b"W/") {
+            let mut weak = b"W/".to_vec();
+            weak.extend_from_slice(etag.as_bytes());
+            let weak = HeaderValue::from_bytes(&weak)?;
+            ctx.resp.headers.insert(ETAG, weak);
+        }
+    }
+    Ok(())
+}
+
 #[cfg(all(test, feature = "tcp", feature = "file"))]
 mod tests {
     use crate::body::DispositionType::*;
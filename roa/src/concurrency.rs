@@ -0,0 +1,242 @@
+//! This module provides a middleware `ConcurrencyLimit`,
+//! which limits in-flight requests by an async semaphore.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::concurrency::ConcurrencyLimit;
+//! use roa::preload::*;
+//! use roa::router::Router;
+//! use roa::{App, Context};
+//! use std::time::Duration;
+//!
+//! #[derive(Clone)]
+//! struct State {
+//!     limit: ConcurrencyLimit,
+//! }
+//!
+//! async fn health(ctx: &mut Context<State>) -> roa::Result {
+//!     let (in_flight, waiting) = (ctx.limit.in_flight(), ctx.limit.waiting());
+//!     ctx.write(format!("in-flight: {}, waiting: {}", in_flight, waiting));
+//!     Ok(())
+//! }
+//!
+//! async fn report(ctx: &mut Context<State>) -> roa::Result {
+//!     Ok(())
+//! }
+//!
+//! let limit = ConcurrencyLimit::new(1024).queue(256, Duration::from_secs(1));
+//! let router = Router::new()
+//!     .get("/health", health)
+//!     .gate(ConcurrencyLimit::new(4))
+//!     .get("/report", report);
+//! let app = App::state(State { limit: limit.clone() })
+//!     .gate(limit)
+//!     .end(router.routes("/").unwrap());
+//! ```
+
+use crate::http::header::{HeaderValue, RETRY_AFTER};
+use crate::http::StatusCode;
+use crate::{async_trait, Context, Middleware, Next, Result, Status};
+use futures::future::{select, Either};
+use futures_timer::Delay;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Shared counters.
+#[derive(Debug)]
+struct Counter {
+    semaphore: Semaphore,
+    waiting: AtomicUsize,
+}
+
+/// A guard to decrease waiting counter when dropped.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A middleware to limit in-flight requests.
+///
+/// Excess requests are shed right away by default,
+/// or wait in a bounded queue for a bounded time if `ConcurrencyLimit::queue` is set.
+/// Shed requests get 503 SERVICE UNAVAILABLE and header "Retry-After".
+///
+/// Clones share the same counters, so a limit can be shared by several routers,
+/// and its counts can be reported by health checks.
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimit {
+    max: usize,
+    queue: usize,
+    wait: Duration,
+    retry_after: Duration,
+    counter: Arc<Counter>,
+}
+
+impl ConcurrencyLimit {
+    /// Construct a middleware allowing at most `max` in-flight requests.
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            queue: 0,
+            wait: Duration::from_secs(0),
+            retry_after: Duration::from_secs(1),
+            counter: Arc::new(Counter {
+                semaphore: Semaphore::new(max),
+                waiting: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Let at most `size` excess requests wait at most `wait` for a permit.
+    pub fn queue(mut self, size: usize, wait: Duration) -> Self {
+        self.queue = size;
+        self.wait = wait;
+        self
+    }
+
+    /// Set value of "Retry-After" for shed requests, one second by default.
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Max in-flight requests.
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Current in-flight requests.
+    pub fn in_flight(&self) -> usize {
+        self.max - self.counter.semaphore.available_permits()
+    }
+
+    /// Current waiting requests.
+    pub fn waiting(&self) -> usize {
+        self.counter.waiting.load(Ordering::SeqCst)
+    }
+
+    /// Try to enter the queue.
+    #[inline]
+    fn enqueue(&self) -> Option<Waiting<'_>> {
+        let waiting = &self.counter.waiting;
+        if waiting.fetch_add(1, Ordering::SeqCst) < self.queue {
+            Some(Waiting(waiting))
+        } else {
+            waiting.fetch_sub(1, Ordering::SeqCst);
+            None
+        }
+    }
+}
+
+#[async_trait(? Send)]
+impl<'a, S> Middleware<'a, S> for ConcurrencyLimit {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let semaphore = &self.counter.semaphore;
+        let permit = match semaphore.try_acquire() {
+            Ok(permit) => Some(permit),
+            Err(_) => match self.enqueue() {
+                Some(_waiting) => {
+                    let acquire = Box::pin(semaphore.acquire());
+                    match select(acquire, Delay::new(self.wait)).await {
+                        Either::Left((permit, _)) => Some(permit),
+                        Either::Right(_) => None,
+                    }
+                }
+                None => None,
+            },
+        };
+        match permit {
+            Some(_permit) => next.await,
+            None => {
+                let retry_after = self.retry_after.as_secs().max(1);
+                ctx.resp
+                    .headers
+                    .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+                Err(Status::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "too many concurrent requests",
+                    true,
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::ConcurrencyLimit;
+    use crate::http::header::RETRY_AFTER;
+    use crate::http::StatusCode;
+    use crate::testing::TestClient;
+    use crate::{App, Context};
+    use futures::future::join;
+    use futures_timer::Delay;
+    use std::time::Duration;
+
+    async fn slow(_ctx: &mut Context) -> crate::Result {
+        Delay::new(Duration::from_millis(100)).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn shed() -> Result<(), Box<dyn std::error::Error>> {
+        let limit = ConcurrencyLimit::new(1).retry_after(Duration::from_secs(3));
+        let client = TestClient::new(&App::new().gate(limit.clone()).end(slow));
+        let check = async {
+            Delay::new(Duration::from_millis(20)).await;
+            assert_eq!(1, limit.in_flight());
+            let resp = client.get("/").send().await?;
+            assert_eq!(StatusCode::SERVICE_UNAVAILABLE, resp.status);
+            assert_eq!("3", resp.headers[RETRY_AFTER]);
+            Ok::<_, std::io::Error>(())
+        };
+        let (resp, result) = join(client.get("/").send(), check).await;
+        assert_eq!(StatusCode::OK, resp?.status);
+        result?;
+        assert_eq!(0, limit.in_flight());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn queue() -> Result<(), Box<dyn std::error::Error>> {
+        let limit = ConcurrencyLimit::new(1).queue(1, Duration::from_secs(1));
+        let client = TestClient::new(&App::new().gate(limit.clone()).end(slow));
+        let check = async {
+            Delay::new(Duration::from_millis(20)).await;
+            let waiting = client.get("/").send();
+            let shed = async {
+                Delay::new(Duration::from_millis(20)).await;
+                assert_eq!(1, limit.waiting());
+                client.get("/").send().await
+            };
+            let (waiting, shed) = join(waiting, shed).await;
+            assert_eq!(StatusCode::OK, waiting?.status);
+            assert_eq!(StatusCode::SERVICE_UNAVAILABLE, shed?.status);
+            Ok::<_, std::io::Error>(())
+        };
+        let (resp, result) = join(client.get("/").send(), check).await;
+        assert_eq!(StatusCode::OK, resp?.status);
+        result?;
+        assert_eq!(0, limit.waiting());
+
+        // wait timeout
+        let limit = ConcurrencyLimit::new(1).queue(1, Duration::from_millis(10));
+        let client = TestClient::new(&App::new().gate(limit).end(slow));
+        let (first, second) = join(client.get("/").send(), async {
+            Delay::new(Duration::from_millis(20)).await;
+            client.get("/").send().await
+        })
+        .await;
+        assert_eq!(StatusCode::OK, first?.status);
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, second?.status);
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
 //! This module provides a middleware `cookie_parser` and context extensions `CookieGetter` and `CookieSetter`.
 //!
+//! Signed and private (encrypted) cookies are supported by context extension `SecureCookie`
+//! if the state implements `CookieKeys`, with feature "secure_cookies" enabled.
+//!
 //! ### Example
 //!
 //! ```rust
@@ -23,13 +26,66 @@
 
 use crate::http::{header, StatusCode};
 use crate::{throw, Context, Next, Result};
-pub use cookie::Cookie;
+#[cfg(feature = "secure_cookies")]
+use cookie::CookieJar;
+#[cfg(feature = "secure_cookies")]
+pub use cookie::Key;
+pub use cookie::{Cookie, SameSite};
 use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+#[cfg(feature = "secure_cookies")]
+use std::ops::Deref;
 use std::sync::Arc;
 
 /// A scope to store and load variables in Context::storage.
 struct CookieScope;
 
+/// Keys to sign or encrypt cookies.
+///
+/// Cookies are signed or encrypted by the current key,
+/// and old keys are only used to verify or decrypt cookies, for key rotation.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::cookie::{Keys, Key};
+/// use roa::App;
+///
+/// let keys = Keys::new(Key::generate()).old(Key::generate());
+/// let app = App::state(keys);
+/// ```
+#[cfg(feature = "secure_cookies")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "secure_cookies")))]
+#[derive(Clone)]
+pub struct Keys {
+    current: Key,
+    old: Vec<Key>,
+}
+
+/// A state providing keys of signed and private cookies.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::cookie::{CookieKeys, Keys, Key};
+///
+/// #[derive(Clone)]
+/// struct State {
+///     keys: Keys,
+/// }
+///
+/// impl CookieKeys for State {
+///     fn cookie_keys(&self) -> &Keys {
+///         &self.keys
+///     }
+/// }
+/// ```
+#[cfg(feature = "secure_cookies")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "secure_cookies")))]
+pub trait CookieKeys {
+    /// Keys of signed and private cookies.
+    fn cookie_keys(&self) -> &Keys;
+}
+
 /// A context extension.
 /// This extension must be used in downstream of middleware `cookier_parser`,
 /// otherwise you cannot get expected cookie.
@@ -110,6 +166,45 @@ pub trait CookieSetter {
     fn set_cookie(&mut self, cookie: Cookie<'_>) -> Result;
 }
 
+/// A context extension to get and set signed or private cookies.
+/// Getters must be used in downstream of middleware `cookier_parser`.
+#[cfg(feature = "secure_cookies")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "secure_cookies")))]
+pub trait SecureCookie {
+    /// Try to get a signed cookie and verify it,
+    /// return `None` if it not exists or it's tampered.
+    fn signed_cookie(&self, name: &str) -> Option<Cookie<'static>>;
+
+    /// Try to get a private cookie and decrypt it,
+    /// return `None` if it not exists or it's tampered.
+    fn private_cookie(&self, name: &str) -> Option<Cookie<'static>>;
+
+    /// Sign a cookie by current key and set it.
+    /// The value is readable, but cannot be tampered.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::cookie::{cookie_parser, Cookie, Key, Keys};
+    /// use roa::preload::*;
+    /// use roa::{App, Context};
+    ///
+    /// async fn end(ctx: &mut Context<Keys>) -> roa::Result {
+    ///     let theme = ctx.signed_cookie("theme");
+    ///     ctx.set_signed_cookie(Cookie::new("theme", "dark"))
+    /// }
+    ///
+    /// let app = App::state(Keys::new(Key::generate()))
+    ///     .gate(cookie_parser)
+    ///     .end(end);
+    /// ```
+    fn set_signed_cookie(&mut self, cookie: Cookie<'static>) -> Result;
+
+    /// Encrypt a cookie by current key and set it.
+    /// The value is neither readable nor tamperable.
+    fn set_private_cookie(&mut self, cookie: Cookie<'static>) -> Result;
+}
+
 /// A middleware to parse cookie.
 #[inline]
 pub async fn cookie_parser<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
@@ -130,6 +225,71 @@ pub async fn cookie_parser<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
     next.await
 }
 
+#[cfg(feature = "secure_cookies")]
+impl Keys {
+    /// Construct keys with the current key.
+    pub fn new(current: Key) -> Self {
+        Self {
+            current,
+            old: Vec::new(),
+        }
+    }
+
+    /// Add an old key, which is only used to verify or decrypt cookies.
+    pub fn old(mut self, key: Key) -> Self {
+        self.old.push(key);
+        self
+    }
+
+    /// Iterate keys, from current to old ones.
+    fn iter(&self) -> impl Iterator<Item = &Key> {
+        std::iter::once(&self.current).chain(self.old.iter())
+    }
+}
+
+#[cfg(feature = "secure_cookies")]
+impl CookieKeys for Keys {
+    #[inline]
+    fn cookie_keys(&self) -> &Keys {
+        self
+    }
+}
+
+/// Verify or decrypt a cookie by keys.
+#[cfg(feature = "secure_cookies")]
+pub(crate) fn open(
+    keys: &Keys,
+    cookie: &Cookie<'static>,
+    private: bool,
+) -> Option<Cookie<'static>> {
+    keys.iter().find_map(|key| {
+        let mut jar = CookieJar::new();
+        jar.add_original(cookie.clone());
+        if private {
+            jar.private(key).get(cookie.name())
+        } else {
+            jar.signed(key).get(cookie.name())
+        }
+    })
+}
+
+/// Sign or encrypt a cookie by current key.
+#[cfg(feature = "secure_cookies")]
+pub(crate) fn seal(
+    keys: &Keys,
+    cookie: Cookie<'static>,
+    private: bool,
+) -> Option<Cookie<'static>> {
+    let name = cookie.name().to_string();
+    let mut jar = CookieJar::new();
+    if private {
+        jar.private(&keys.current).add(cookie);
+    } else {
+        jar.signed(&keys.current).add(cookie);
+    }
+    jar.get(&name).cloned()
+}
+
 impl<S> CookieGetter for Context<S> {
     #[inline]
     fn must_cookie(&mut self, name: &str) -> Result<Arc<Cookie<'static>>> {
@@ -166,14 +326,49 @@ impl<S> CookieSetter for Context<S> {
     }
 }
 
+#[cfg(feature = "secure_cookies")]
+impl<S: CookieKeys> SecureCookie for Context<S> {
+    #[inline]
+    fn signed_cookie(&self, name: &str) -> Option<Cookie<'static>> {
+        open(self.deref().cookie_keys(), &*self.cookie(name)?, false)
+    }
+
+    #[inline]
+    fn private_cookie(&self, name: &str) -> Option<Cookie<'static>> {
+        open(self.deref().cookie_keys(), &*self.cookie(name)?, true)
+    }
+
+    #[inline]
+    fn set_signed_cookie(&mut self, cookie: Cookie<'static>) -> Result {
+        match seal(self.deref().cookie_keys(), cookie, false) {
+            Some(cookie) => self.set_cookie(cookie),
+            None => throw!(StatusCode::INTERNAL_SERVER_ERROR, "fail to sign cookie"),
+        }
+    }
+
+    #[inline]
+    fn set_private_cookie(&mut self, cookie: Cookie<'static>) -> Result {
+        match seal(self.deref().cookie_keys(), cookie, true) {
+            Some(cookie) => self.set_cookie(cookie),
+            None => throw!(StatusCode::INTERNAL_SERVER_ERROR, "fail to encrypt cookie"),
+        }
+    }
+}
+
 #[cfg(all(test, feature = "tcp"))]
 mod tests {
     use crate::cookie::{cookie_parser, Cookie};
+    #[cfg(feature = "secure_cookies")]
+    use crate::cookie::{Key, Keys};
+    #[cfg(feature = "secure_cookies")]
+    use crate::http::header::SET_COOKIE;
     use crate::http::{
         header::{COOKIE, WWW_AUTHENTICATE},
         StatusCode,
     };
     use crate::preload::*;
+    #[cfg(feature = "secure_cookies")]
+    use crate::testing::TestClient;
     use crate::{App, Context};
     use async_std::task::spawn;
 
@@ -285,4 +480,99 @@ mod tests {
         assert_eq!(("foo%20baz"), cookies[1].value());
         Ok(())
     }
+
+    #[cfg(feature = "secure_cookies")]
+    async fn keyed(ctx: &mut Context<Keys>) -> crate::Result {
+        if ctx.uri().path() == "/set" {
+            ctx.set_signed_cookie(Cookie::new("theme", "dark"))?;
+            ctx.set_private_cookie(Cookie::new("token", "secret"))?;
+        }
+        let theme = ctx.signed_cookie("theme");
+        let token = ctx.private_cookie("token");
+        ctx.write(format!(
+            "{} {}",
+            theme.as_ref().map(Cookie::value).unwrap_or("none"),
+            token.as_ref().map(Cookie::value).unwrap_or("none")
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "secure_cookies")]
+    /// Get "name=value" pairs of "Set-Cookie".
+    async fn set_cookies(keys: Keys) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let client = TestClient::new(&App::state(keys).gate(cookie_parser).end(keyed));
+        let resp = client.get("/set").send().await?;
+        let mut cookies = Vec::new();
+        for value in resp.headers.get_all(SET_COOKIE) {
+            cookies.push(value.to_str()?.split(';').next().unwrap().to_string());
+        }
+        Ok(cookies)
+    }
+
+    #[cfg(feature = "secure_cookies")]
+    #[tokio::test]
+    async fn signed_and_private() -> Result<(), Box<dyn std::error::Error>> {
+        let keys = Keys::new(Key::generate());
+        let cookies = set_cookies(keys.clone()).await?;
+        assert_eq!(2, cookies.len());
+        assert!(cookies[0].starts_with("theme=") && cookies[0].ends_with("dark"));
+        assert!(!cookies[1].contains("secret"));
+
+        let client = TestClient::new(&App::state(keys).gate(cookie_parser).end(keyed));
+        let resp = client
+            .get("/")
+            .header(COOKIE, cookies.join("; "))
+            .send()
+            .await?;
+        assert_eq!("dark secret", resp.text().await?);
+
+        // tampered
+        let tampered = cookies[0].replace("dark", "light");
+        let resp = client
+            .get("/")
+            .header(COOKIE, format!("{}; {}x", tampered, cookies[1]))
+            .send()
+            .await?;
+        assert_eq!("none none", resp.text().await?);
+
+        // plain
+        let resp = client
+            .get("/")
+            .header(COOKIE, "theme=dark; token=secret")
+            .send()
+            .await?;
+        assert_eq!("none none", resp.text().await?);
+        Ok(())
+    }
+
+    #[cfg(feature = "secure_cookies")]
+    #[tokio::test]
+    async fn key_rotation() -> Result<(), Box<dyn std::error::Error>> {
+        let old = Key::generate();
+        let cookies = set_cookies(Keys::new(old.clone())).await?;
+
+        let rotated = Keys::new(Key::generate()).old(old);
+        let client =
+            TestClient::new(&App::state(rotated).gate(cookie_parser).end(keyed));
+        let resp = client
+            .get("/")
+            .header(COOKIE, cookies.join("; "))
+            .send()
+            .await?;
+        assert_eq!("dark secret", resp.text().await?);
+
+        // unknown key
+        let client = TestClient::new(
+            &App::state(Keys::new(Key::generate()))
+                .gate(cookie_parser)
+                .end(keyed),
+        );
+        let resp = client
+            .get("/")
+            .header(COOKIE, cookies.join("; "))
+            .send()
+            .await?;
+        assert_eq!("none none", resp.text().await?);
+        Ok(())
+    }
 }
@@ -0,0 +1,477 @@
+//! This module provides a middleware `Csrf` and a context extension `CsrfToken`,
+//! to protect form posts from cross-site request forgery.
+//!
+//! The token is stored in a signed cookie (double-submit) by default, or in the session.
+//! Requests in unsafe methods must submit it by header "X-CSRF-Token" or form field "_csrf".
+//!
+//! ### Example
+//!
+//! ```rust
+//! use askama::Template;
+//! use roa::cookie::{cookie_parser, Key, Keys};
+//! use roa::csrf::Csrf;
+//! use roa::preload::*;
+//! use roa::router::Router;
+//! use roa::{App, Context};
+//!
+//! #[derive(Template)]
+//! #[template(
+//!     source = r#"<form method="post"><input type="hidden" name="_csrf" value="{{ csrf }}"></form>"#,
+//!     ext = "html"
+//! )]
+//! struct Form {
+//!     csrf: String,
+//! }
+//!
+//! async fn form(ctx: &mut Context) -> roa::Result {
+//!     let csrf = ctx.csrf_token()?;
+//!     ctx.render(&Form { csrf })
+//! }
+//!
+//! async fn submit(ctx: &mut Context) -> roa::Result {
+//!     // body is read by `Csrf` already, it's not read twice.
+//!     let data = ctx.read().await?;
+//!     Ok(())
+//! }
+//!
+//! let router = Router::new().get("/", form).post("/", submit);
+//! let app = App::new()
+//!     .gate(cookie_parser)
+//!     .gate(Csrf::new(Keys::new(Key::generate())).exempt("/webhook"))
+//!     .end(router.routes("/").unwrap());
+//! ```
+
+use crate::body::{keep_body, PowerBody};
+use crate::cookie::{open, seal, Cookie, CookieGetter, CookieSetter, Keys, SameSite};
+use crate::http::header::{HeaderName, CONTENT_TYPE};
+use crate::http::{Method, StatusCode};
+use crate::{async_trait, throw, Context, Middleware, Next, Result, State, Status};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use std::collections::HashSet;
+
+#[cfg(feature = "session")]
+use crate::session::SessionGetter;
+
+/// A private scope to store csrf config and token in Context::storage.
+struct CsrfScope;
+
+/// Length of csrf token.
+const TOKEN_LENGTH: usize = 32;
+
+/// Key of csrf token in session.
+#[cfg(feature = "session")]
+const SESSION_KEY: &str = "csrf";
+
+/// Where the csrf token is stored.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Store {
+    Cookie,
+    #[cfg(feature = "session")]
+    Session,
+}
+
+/// A middleware to protect requests in unsafe methods from cross-site request forgery.
+///
+/// Requests in methods except GET, HEAD, OPTIONS and TRACE are rejected with 403 FORBIDDEN
+/// if the submitted token doesn't match the stored one.
+///
+/// Token is submitted by header, or by field of an urlencoded form.
+/// Form body is read by this middleware and kept for `PowerBody::read`.
+///
+/// The token cookie is signed by `keys` and "HttpOnly",
+/// cookies failing verification are ignored, so they cannot be forged by other sites
+/// sharing the domain. Templates should get the token by `CsrfToken::csrf_token`.
+///
+/// This middleware must be used in downstream of `cookie_parser`,
+/// and `SessionManager` if tokens are stored in session.
+#[derive(Clone)]
+pub struct Csrf {
+    keys: Keys,
+    store: Store,
+    cookie: String,
+    secure: bool,
+    same_site: SameSite,
+    header: HeaderName,
+    field: String,
+    exempt: HashSet<String>,
+}
+
+/// A context extension.
+/// This extension must be used in downstream of middleware `Csrf`.
+pub trait CsrfToken {
+    /// Get csrf token, generate and store it if it not exists.
+    fn csrf_token(&mut self) -> Result<String>;
+}
+
+/// Generate a random token.
+fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .collect()
+}
+
+/// Compare tokens in constant time.
+fn token_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+/// Throw a internal server error.
+#[inline]
+fn csrf_not_set() -> Status {
+    Status::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "middleware `Csrf` is not set correctly",
+        false,
+    )
+}
+
+impl Csrf {
+    /// Construct a middleware storing tokens in cookie signed by keys.
+    pub fn new(keys: Keys) -> Self {
+        Self {
+            keys,
+            store: Store::Cookie,
+            cookie: "roa.csrf".to_string(),
+            secure: false,
+            same_site: SameSite::Lax,
+            header: HeaderName::from_static("x-csrf-token"),
+            field: "_csrf".to_string(),
+            exempt: HashSet::new(),
+        }
+    }
+
+    /// Store tokens in session instead of cookie.
+    #[cfg(feature = "session")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "session")))]
+    pub fn session(mut self) -> Self {
+        self.store = Store::Session;
+        self
+    }
+
+    /// Set name of token cookie, "roa.csrf" by default.
+    pub fn cookie(mut self, name: impl ToString) -> Self {
+        self.cookie = name.to_string();
+        self
+    }
+
+    /// Set "Secure" attribute of token cookie, false by default.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Set "SameSite" attribute of token cookie, "Lax" by default.
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    /// Set header to submit token, "X-CSRF-Token" by default.
+    pub fn header(mut self, name: HeaderName) -> Self {
+        self.header = name;
+        self
+    }
+
+    /// Set form field to submit token, "_csrf" by default.
+    pub fn field(mut self, name: impl ToString) -> Self {
+        self.field = name.to_string();
+        self
+    }
+
+    /// Skip checking requests to the path.
+    pub fn exempt(mut self, path: impl ToString) -> Self {
+        self.exempt.insert(normalize(&path.to_string()).to_string());
+        self
+    }
+
+    /// Load stored token.
+    fn stored<S>(&self, ctx: &Context<S>) -> Result<Option<String>> {
+        match self.store {
+            Store::Cookie => Ok(ctx
+                .cookie(&self.cookie)
+                .and_then(|cookie| open(&self.keys, &cookie, false))
+                .map(|cookie| cookie.value().to_string())
+                .filter(|token| !token.is_empty())),
+            #[cfg(feature = "session")]
+            Store::Session => ctx.session()?.get(SESSION_KEY),
+        }
+    }
+
+    /// Store a new token.
+    fn store<S>(&self, ctx: &mut Context<S>, token: &str) -> Result {
+        match self.store {
+            Store::Cookie => {
+                let cookie = Cookie::build(self.cookie.clone(), token.to_string())
+                    .path("/")
+                    .http_only(true)
+                    .secure(self.secure)
+                    .same_site(self.same_site)
+                    .finish();
+                match seal(&self.keys, cookie, false) {
+                    Some(cookie) => ctx.set_cookie(cookie),
+                    None => {
+                        throw!(StatusCode::INTERNAL_SERVER_ERROR, "fail to sign cookie")
+                    }
+                }
+            }
+            #[cfg(feature = "session")]
+            Store::Session => ctx.session()?.set(SESSION_KEY, &token),
+        }
+    }
+
+    /// Get token submitted by header or form.
+    async fn submitted<S: State>(&self, ctx: &mut Context<S>) -> Result<Option<String>> {
+        if let Some(token) = ctx.req.headers.get(&self.header) {
+            return Ok(token.to_str().ok().map(ToString::to_string));
+        }
+        let is_form = ctx
+            .req
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.starts_with("application/x-www-form-urlencoded"))
+            .unwrap_or(false);
+        if !is_form {
+            return Ok(None);
+        }
+        let data = ctx.read().await?;
+        let token = url::form_urlencoded::parse(&data)
+            .find(|(name, _)| *name == self.field)
+            .map(|(_, value)| value.into_owned());
+        keep_body(ctx, data);
+        Ok(token)
+    }
+}
+
+/// Trim trailing slash of path.
+fn normalize(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" => "/",
+        path => path,
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S: State> Middleware<'a, S> for Csrf {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let stored = self.stored(ctx)?;
+        let safe = matches!(
+            *ctx.method(),
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+        );
+        if !safe && !self.exempt.contains(normalize(ctx.uri().path())) {
+            let submitted = self.submitted(ctx).await?;
+            match (&stored, submitted) {
+                (None, _) => throw!(StatusCode::FORBIDDEN, "csrf token is not issued"),
+                (_, None) => throw!(StatusCode::FORBIDDEN, "csrf token is missing"),
+                (Some(stored), Some(submitted)) if !token_eq(stored, &submitted) => {
+                    throw!(StatusCode::FORBIDDEN, "csrf token is invalid")
+                }
+                _ => (),
+            }
+        }
+        ctx.store_scoped(CsrfScope, "csrf", self.clone());
+        if let Some(token) = stored {
+            ctx.store_scoped(CsrfScope, "token", token);
+        }
+        next.await
+    }
+}
+
+impl<S> CsrfToken for Context<S> {
+    #[inline]
+    fn csrf_token(&mut self) -> Result<String> {
+        if let Some(token) = self.load_scoped::<CsrfScope, String>("token") {
+            return Ok((*token).clone());
+        }
+        let csrf = match self.load_scoped::<CsrfScope, Csrf>("csrf") {
+            Some(csrf) => (*csrf).clone(),
+            None => return Err(csrf_not_set()),
+        };
+        let token = generate_token();
+        csrf.store(self, &token)?;
+        self.store_scoped(CsrfScope, "token", token.clone());
+        Ok(token)
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{token_eq, Csrf};
+    use crate::cookie::{cookie_parser, Key, Keys, SameSite};
+    use crate::http::header::{CONTENT_TYPE, COOKIE, SET_COOKIE};
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::testing::TestClient;
+    use crate::{App, Context};
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        if *ctx.method() == crate::http::Method::GET {
+            let token = ctx.csrf_token()?;
+            ctx.write(token);
+        } else {
+            let data = ctx.read().await?;
+            ctx.write(data);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn compare_token() {
+        assert!(token_eq("abc", "abc"));
+        assert!(!token_eq("abc", "abd"));
+        assert!(!token_eq("abc", "ab"));
+    }
+
+    #[tokio::test]
+    async fn double_submit() -> Result<(), Box<dyn std::error::Error>> {
+        let app = App::new()
+            .gate(cookie_parser)
+            .gate(Csrf::new(Keys::new(Key::generate())).exempt("/webhook/"))
+            .end(end);
+        let client = TestClient::new(&app);
+
+        let resp = client.get("/").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        let cookie = resp.headers[SET_COOKIE].to_str()?.to_string();
+        assert!(cookie.contains("SameSite=Lax"));
+        assert!(cookie.contains("HttpOnly"));
+        assert!(!cookie.contains("Secure"));
+        let cookie = cookie.split(';').next().unwrap().to_string();
+        let token = resp.text().await?;
+        assert!(cookie.starts_with("roa.csrf="));
+        assert!(cookie.ends_with(&token));
+        assert_ne!(format!("roa.csrf={}", token), cookie);
+
+        // token is issued only once.
+        let resp = client
+            .get("/")
+            .header(COOKIE, cookie.as_str())
+            .send()
+            .await?;
+        assert!(resp.headers.get(SET_COOKIE).is_none());
+        assert_eq!(token, resp.text().await?);
+
+        let resp = client.post("/").body("data").send().await?;
+        assert_eq!(StatusCode::FORBIDDEN, resp.status);
+        assert!(resp.text().await?.contains("not issued"));
+
+        let resp = client
+            .post("/")
+            .header(COOKIE, cookie.as_str())
+            .body("data")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::FORBIDDEN, resp.status);
+        assert!(resp.text().await?.contains("missing"));
+
+        let resp = client
+            .post("/")
+            .header(COOKIE, cookie.as_str())
+            .header("x-csrf-token", "forged")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::FORBIDDEN, resp.status);
+        assert!(resp.text().await?.contains("invalid"));
+
+        let resp = client
+            .post("/")
+            .header(COOKIE, cookie.as_str())
+            .header("x-csrf-token", token.as_str())
+            .body("data")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("data", resp.text().await?);
+
+        // body of form is kept.
+        let form = format!("name=Hexilee&_csrf={}", token);
+        let resp = client
+            .post("/")
+            .header(COOKIE, cookie.as_str())
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(form.clone())
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!(form, resp.text().await?);
+
+        // unsigned or forged cookie is ignored.
+        for forged in &[format!("roa.csrf={}", token), "roa.csrf=forged".to_string()] {
+            let resp = client
+                .post("/")
+                .header(COOKIE, forged.as_str())
+                .header("x-csrf-token", &forged["roa.csrf=".len()..])
+                .body("data")
+                .send()
+                .await?;
+            assert_eq!(StatusCode::FORBIDDEN, resp.status);
+            assert!(resp.text().await?.contains("not issued"));
+        }
+
+        let resp = client.post("/webhook").body("data").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cookie_attributes() -> Result<(), Box<dyn std::error::Error>> {
+        let app = App::new()
+            .gate(cookie_parser)
+            .gate(
+                Csrf::new(Keys::new(Key::generate()))
+                    .secure(true)
+                    .same_site(SameSite::Strict),
+            )
+            .end(end);
+        let resp = TestClient::new(&app).get("/").send().await?;
+        let cookie = resp.headers[SET_COOKIE].to_str()?;
+        assert!(cookie.contains("Secure"));
+        assert!(cookie.contains("SameSite=Strict"));
+        Ok(())
+    }
+
+    #[cfg(feature = "session")]
+    #[tokio::test]
+    async fn session() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::session::{Key, MemoryStore, SessionManager};
+        let app = App::new()
+            .gate(cookie_parser)
+            .gate(SessionManager::new(Key::generate(), MemoryStore::new()))
+            .gate(Csrf::new(Keys::new(Key::generate())).session())
+            .end(end);
+        let client = TestClient::new(&app);
+
+        let resp = client.get("/").send().await?;
+        let cookie = resp.headers[SET_COOKIE].to_str()?.to_string();
+        assert!(cookie.starts_with("roa.sid="));
+        let cookie = cookie.split(';').next().unwrap().to_string();
+        let token = resp.text().await?;
+
+        let resp = client
+            .post("/")
+            .header(COOKIE, cookie.as_str())
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(format!("_csrf={}", token))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+
+        let resp = client
+            .post("/")
+            .header(COOKIE, cookie.as_str())
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body("_csrf=forged")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::FORBIDDEN, resp.status);
+        Ok(())
+    }
+}
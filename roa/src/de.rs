@@ -0,0 +1,391 @@
+//! A deserializer of string variables, like router parameters or query.
+
+use crate::http::StatusCode;
+use crate::Status;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::vec::IntoIter;
+
+/// A tree of string variables.
+pub enum Value {
+    Str(String),
+    #[cfg_attr(not(feature = "urlencoded"), allow(dead_code))]
+    Seq(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+/// Error occurring in deserializing variables.
+#[derive(Debug)]
+pub struct Error {
+    message: String,
+    /// Whether the message names the bad variable.
+    named: bool,
+}
+
+/// Deserializer of a variable.
+struct Deserializer {
+    key: String,
+    value: Value,
+}
+
+struct MapDeserializer {
+    key: String,
+    iter: IntoIter<(String, Value)>,
+    value: Option<(String, Value)>,
+}
+
+struct SeqDeserializer {
+    key: String,
+    iter: IntoIter<Value>,
+}
+
+/// Deserialize variables into `T`, any error is converted to 400 BAD REQUEST.
+pub fn from_value<T>(value: Value) -> Result<T, Status>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(Deserializer {
+        key: String::new(),
+        value,
+    })
+    .map_err(|err| Status::new(StatusCode::BAD_REQUEST, err.message, true))
+}
+
+impl Value {
+    /// Build a tree from pairs like `tag=a&tag=b&user[name]=Hexilee&id[]=0`.
+    ///
+    /// Repeated keys and keys end with `[]` are collected as a sequence,
+    /// keys like `user[name]` are nested.
+    #[cfg(feature = "urlencoded")]
+    pub fn from_pairs<'a>(
+        pairs: impl IntoIterator<Item = &'a (String, String)>,
+    ) -> Self {
+        let mut entries = Vec::new();
+        for (key, value) in pairs {
+            insert(&mut entries, &split_key(key), value.clone());
+        }
+        Value::Map(entries)
+    }
+}
+
+/// Split key like `a[b][c]` into `["a", "b", "c"]`.
+#[cfg(feature = "urlencoded")]
+fn split_key(key: &str) -> Vec<&str> {
+    match key.find('[') {
+        Some(index) if index > 0 && key.ends_with(']') => {
+            let mut path = vec![&key[..index]];
+            path.extend(key[index + 1..key.len() - 1].split("]["));
+            path
+        }
+        _ => vec![key],
+    }
+}
+
+/// Insert a value into entries by path.
+#[cfg(feature = "urlencoded")]
+fn insert(entries: &mut Vec<(String, Value)>, path: &[&str], value: String) {
+    let (key, rest) = match path.split_first() {
+        Some(pair) => pair,
+        None => return,
+    };
+    let entry = entries.iter_mut().find(|(name, _)| name == key);
+    match rest {
+        // `key=value` or `key[]=value`
+        [] | [""] => match entry {
+            None if rest.is_empty() => {
+                entries.push((key.to_string(), Value::Str(value)))
+            }
+            None => entries.push((key.to_string(), Value::Seq(vec![Value::Str(value)]))),
+            Some((_, Value::Seq(values))) => values.push(Value::Str(value)),
+            Some((_, old @ Value::Str(_))) => {
+                let first = std::mem::replace(old, Value::Seq(Vec::new()));
+                *old = Value::Seq(vec![first, Value::Str(value)]);
+            }
+            // conflict with nested keys, ignore it.
+            Some((_, Value::Map(_))) => (),
+        },
+        _ => match entry {
+            Some((_, Value::Map(children))) => insert(children, rest, value),
+            None => {
+                let mut children = Vec::new();
+                insert(&mut children, rest, value);
+                entries.push((key.to_string(), Value::Map(children)));
+            }
+            // conflict with a value, ignore it.
+            Some(_) => (),
+        },
+    }
+}
+
+impl Error {
+    /// Name the bad variable if it's not named.
+    fn named(mut self, key: &str) -> Self {
+        if !self.named {
+            self.message = format!("{}\nvariable `{}` is invalid", self.message, key);
+            self.named = true;
+        }
+        self
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self {
+            message: msg.to_string(),
+            named: false,
+        }
+    }
+}
+
+impl Deserializer {
+    /// Get a single string value.
+    fn into_str(self) -> Result<(String, String), Error> {
+        match self.value {
+            Value::Str(value) => Ok((self.key, value)),
+            Value::Seq(mut values) if values.len() == 1 => Self {
+                key: self.key,
+                value: values.remove(0),
+            }
+            .into_str(),
+            _ => Err(Error {
+                message: format!("variable `{}` should be a single value", self.key),
+                named: true,
+            }),
+        }
+    }
+
+    /// Parse a single string value.
+    fn parse<T>(self) -> Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let (key, value) = self.into_str()?;
+        value.parse().map_err(|err| Error {
+            message: format!(
+                "{}\ntype of variable `{}` should be {}",
+                err,
+                key,
+                std::any::type_name::<T>()
+            ),
+            named: true,
+        })
+    }
+}
+
+macro_rules! impl_parse {
+    ($($deserialize:ident => $visit:ident,)*) => {
+        $(
+            fn $deserialize<V>(self, visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'de>,
+            {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::Str(value) => visitor.visit_string(value),
+            Value::Seq(values) => visitor.visit_seq(SeqDeserializer {
+                key: self.key,
+                iter: values.into_iter(),
+            }),
+            Value::Map(entries) => visitor.visit_map(MapDeserializer {
+                key: self.key,
+                iter: entries.into_iter(),
+                value: None,
+            }),
+        }
+    }
+
+    impl_parse! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_string(self.into_str()?.1)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            // a single value is a sequence with one element.
+            Value::Str(value) => visitor.visit_seq(SeqDeserializer {
+                key: self.key,
+                iter: vec![Value::Str(value)].into_iter(),
+            }),
+            value => Self {
+                key: self.key,
+                value,
+            }
+            .deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (_, value) = self.into_str()?;
+        visitor.visit_enum(value.into_deserializer())
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf tuple_struct map struct identifier
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            None => Ok(None),
+            Some((key, value)) => {
+                let result = seed.deserialize(key.clone().into_deserializer());
+                self.value = Some((key, value));
+                result.map(Some)
+            }
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let (key, value) = self
+            .value
+            .take()
+            .ok_or_else(|| de::Error::custom("value is missing"))?;
+        let key = if self.key.is_empty() {
+            key
+        } else {
+            format!("{}[{}]", self.key, key)
+        };
+        seed.deserialize(Deserializer {
+            key: key.clone(),
+            value,
+        })
+        .map_err(|err| err.named(&key))
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for SeqDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            None => Ok(None),
+            Some(value) => seed
+                .deserialize(Deserializer {
+                    key: self.key.clone(),
+                    value,
+                })
+                .map(Some)
+                .map_err(|err| err.named(&self.key)),
+        }
+    }
+}
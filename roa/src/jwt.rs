@@ -1,4 +1,10 @@
-//! This module provides middleware `JwtGuard` and a context extension `JwtVerifier`.
+//! This module provides middleware `JwtGuard` and context extensions `JwtVerifier` and `JwtIssuer`.
+//!
+//! Requests can be authorized by claims with middlewares `require_scope`, `require_any_role` and `require`.
+//!
+//! Tokens can be delivered by header, cookie or query, see `TokenSource`.
+//! Tokens are verified by a `KeySet`, which selects decoding keys by the `kid` header
+//! and can be reloaded from a JWKS document at runtime.
 //!
 //! ### Example
 //!
@@ -68,15 +74,24 @@
 //! }
 //! ```
 
-pub use jsonwebtoken::{DecodingKey, Validation};
+mod authorize;
+mod keys;
+
+pub use authorize::{require, require_any_role, require_scope, Require};
+pub use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+pub use keys::KeySet;
 
-use crate::http::header::{HeaderValue, WWW_AUTHENTICATE};
+use crate::http::header::{HeaderValue, COOKIE, WWW_AUTHENTICATE};
 use crate::http::StatusCode;
-use crate::{async_trait, throw, Context, Middleware, Next, Result, Status};
+use crate::{async_trait, Context, Middleware, Next, Result, Status};
 use headers::{authorization::Bearer, Authorization, HeaderMapExt};
-use jsonwebtoken::decode;
+use jsonwebtoken::errors::{Error as JwtError, ErrorKind};
+use jsonwebtoken::{decode, decode_header, encode};
+use percent_encoding::percent_decode_str;
 use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::Value;
+use std::ops::Deref;
 
 /// A private scope.
 struct JwtScope;
@@ -85,12 +100,72 @@ lazy_static::lazy_static!(
     static ref INVALID_TOKEN: HeaderValue = HeaderValue::from_static(r#"Bearer realm="<jwt>", error="invalid_token""#);
 );
 
-/// A function to set value of WWW_AUTHENTICATE.
+/// Reasons to reject a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rejection {
+    Missing,
+    Expired,
+    Malformed,
+    BadSignature,
+    Invalid,
+}
+
+impl Rejection {
+    /// The error description.
+    #[inline]
+    fn description(self) -> &'static str {
+        match self {
+            Rejection::Missing => "token is missing",
+            Rejection::Expired => "token is expired",
+            Rejection::Malformed => "token is malformed",
+            Rejection::BadSignature => "token signature is invalid",
+            Rejection::Invalid => "token is invalid",
+        }
+    }
+
+    /// The value of WWW_AUTHENTICATE.
+    #[inline]
+    fn www_authenticate(self) -> HeaderValue {
+        match self {
+            Rejection::Missing => INVALID_TOKEN.clone(),
+            Rejection::Expired => HeaderValue::from_static(
+                r#"Bearer realm="<jwt>", error="invalid_token", error_description="token is expired""#,
+            ),
+            Rejection::Malformed => HeaderValue::from_static(
+                r#"Bearer realm="<jwt>", error="invalid_token", error_description="token is malformed""#,
+            ),
+            Rejection::BadSignature => HeaderValue::from_static(
+                r#"Bearer realm="<jwt>", error="invalid_token", error_description="token signature is invalid""#,
+            ),
+            Rejection::Invalid => HeaderValue::from_static(
+                r#"Bearer realm="<jwt>", error="invalid_token", error_description="token is invalid""#,
+            ),
+        }
+    }
+}
+
+impl From<JwtError> for Rejection {
+    #[inline]
+    fn from(err: JwtError) -> Self {
+        match err.kind() {
+            ErrorKind::ExpiredSignature => Rejection::Expired,
+            ErrorKind::InvalidToken
+            | ErrorKind::Base64(_)
+            | ErrorKind::Json(_)
+            | ErrorKind::Utf8(_) => Rejection::Malformed,
+            ErrorKind::InvalidSignature => Rejection::BadSignature,
+            _ => Rejection::Invalid,
+        }
+    }
+}
+
+/// Set value of WWW_AUTHENTICATE and return a 401 UNAUTHORIZED.
 #[inline]
-fn set_www_authenticate<S>(ctx: &mut Context<S>) {
+fn reject<S>(ctx: &mut Context<S>, rejection: Rejection) -> Status {
     ctx.resp
         .headers
-        .insert(WWW_AUTHENTICATE, INVALID_TOKEN.clone());
+        .insert(WWW_AUTHENTICATE, rejection.www_authenticate());
+    Status::new(StatusCode::UNAUTHORIZED, rejection.description(), true)
 }
 
 /// Throw a internal server error.
@@ -104,7 +179,7 @@ fn guard_not_set() -> Status {
 }
 
 /// A context extension.
-/// This extension must be used in downstream of middleware `guard` or `guard_by`,
+/// This extension must be used in downstream of middleware `guard` or `JwtGuard`,
 /// otherwise you cannot get expected claims.
 ///
 /// ### Example
@@ -121,10 +196,17 @@ fn guard_not_set() -> Status {
 /// ```
 pub trait JwtVerifier<S> {
     /// Deserialize claims from token.
+    ///
+    /// Return 401 UNAUTHORIZED if the request is anonymous in soft mode.
     fn claims<C>(&self) -> Result<C>
     where
         C: 'static + DeserializeOwned;
 
+    /// Deserialize claims from token, return `None` if the request is anonymous in soft mode.
+    fn try_claims<C>(&self) -> Result<Option<C>>
+    where
+        C: 'static + DeserializeOwned;
+
     /// Verify token and deserialize claims with a validation.
     /// Use this method if this validation is different from that one of `JwtGuard`.
     fn verify<C>(&mut self, validation: &Validation) -> Result<C>
@@ -132,41 +214,318 @@ pub trait JwtVerifier<S> {
         C: 'static + DeserializeOwned;
 }
 
+/// A token issuer, consisting of an encoding key and a header template.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::jwt::{Algorithm, EncodingKey, Header, Issuer};
+/// use roa::App;
+///
+/// let issuer = Issuer::new(EncodingKey::from_secret(b"123456"))
+///     .header(Header::new(Algorithm::HS512))
+///     .kid("2020-05");
+/// let app = App::state(issuer);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Issuer {
+    key: EncodingKey,
+    header: Header,
+}
+
+impl Issuer {
+    /// Construct an issuer with default header.
+    pub fn new(key: EncodingKey) -> Self {
+        Self {
+            key,
+            header: Header::default(),
+        }
+    }
+
+    /// Set header template.
+    pub fn header(mut self, header: Header) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Set `kid` of header, so that tokens can be verified by the right key after rotation.
+    pub fn kid(mut self, kid: impl ToString) -> Self {
+        self.header.kid = Some(kid.to_string());
+        self
+    }
+}
+
+/// A state providing a token issuer.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::jwt::{Issuer, TokenIssuer};
+///
+/// #[derive(Clone)]
+/// struct State {
+///     issuer: Issuer,
+/// }
+///
+/// impl TokenIssuer for State {
+///     fn token_issuer(&self) -> &Issuer {
+///         &self.issuer
+///     }
+/// }
+/// ```
+pub trait TokenIssuer {
+    /// The token issuer.
+    fn token_issuer(&self) -> &Issuer;
+}
+
+impl TokenIssuer for Issuer {
+    #[inline]
+    fn token_issuer(&self) -> &Issuer {
+        self
+    }
+}
+
+/// A context extension to issue tokens.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::jwt::{EncodingKey, Issuer, KeySet, Validation};
+/// use roa::preload::*;
+/// use roa::{Context, Result};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Claims {
+///     sub: String,
+///     exp: u64,
+/// }
+///
+/// async fn login(ctx: &mut Context<Issuer>) -> Result {
+///     let token = ctx.issue_token(&Claims {
+///         sub: "Hexilee".to_string(),
+///         exp: 4102444800,
+///     })?;
+///     ctx.write(token);
+///     Ok(())
+/// }
+///
+/// async fn refresh(ctx: &mut Context<Issuer>, keys: &KeySet) -> Result {
+///     let refresh_token = ctx.read().await?;
+///     let token = ctx.refresh_token(
+///         &String::from_utf8_lossy(&refresh_token),
+///         keys,
+///         &Validation::default(),
+///         |claims: Claims| Ok(Claims { exp: 4102444800, ..claims }),
+///     )?;
+///     ctx.write(token);
+///     Ok(())
+/// }
+/// ```
+pub trait JwtIssuer<S> {
+    /// Encode claims into a token.
+    fn issue_token<C>(&self, claims: &C) -> Result<String>
+    where
+        S: TokenIssuer,
+        C: Serialize;
+
+    /// Verify a refresh token by keys and validation,
+    /// then map its claims and issue a new token.
+    ///
+    /// If the refresh token is invalid, return 401 UNAUTHORIZED and set response header "WWW-Authenticate".
+    fn refresh_token<C, N, F>(
+        &mut self,
+        token: &str,
+        keys: &KeySet,
+        validation: &Validation,
+        f: F,
+    ) -> Result<String>
+    where
+        S: TokenIssuer,
+        C: DeserializeOwned,
+        N: Serialize,
+        F: FnOnce(C) -> Result<N>;
+}
+
 /// Guard by default validation.
 pub fn guard(secret: DecodingKey) -> JwtGuard {
     JwtGuard::new(secret, Validation::default())
 }
 
+/// A source to find the json web token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenSource {
+    /// Request header "authorization", in format of `Authorization: Bearer <token>`.
+    Header,
+
+    /// A cookie with the name.
+    Cookie(String),
+
+    /// A query parameter with the name.
+    Query(String),
+}
+
+impl TokenSource {
+    /// Find the token in request.
+    #[inline]
+    fn find<S>(&self, ctx: &Context<S>) -> Option<String> {
+        let token = match self {
+            TokenSource::Header => ctx
+                .req
+                .headers
+                .typed_get::<Authorization<Bearer>>()
+                .map(|auth| auth.0.token().to_string()),
+            TokenSource::Cookie(name) => ctx
+                .req
+                .headers
+                .get_all(COOKIE)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(';'))
+                .find_map(|pair| {
+                    let mut iter = pair.trim().splitn(2, '=');
+                    match (iter.next(), iter.next()) {
+                        (Some(key), Some(value)) if key == name => Some(
+                            percent_decode_str(value).decode_utf8_lossy().into_owned(),
+                        ),
+                        _ => None,
+                    }
+                }),
+            TokenSource::Query(name) => {
+                url::form_urlencoded::parse(ctx.req.uri.query()?.as_bytes())
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.into_owned())
+            }
+        };
+        token.filter(|token| !token.is_empty())
+    }
+}
+
 /// A middleware to deny unauthorized requests.
 ///
-/// The json web token should be deliver by request header "authorization",
+/// The json web token is delivered by request header "authorization" by default,
 /// in format of `Authorization: Bearer <token>`.
+/// Use `JwtGuard::sources` to find it in cookies or query parameters.
+///
+/// If request fails to pass verification, return 401 UNAUTHORIZED and set response header "WWW-Authenticate",
+/// with an "error_description" if the token is expired, malformed or of a bad signature.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::jwt::{DecodingKey, JwtGuard, TokenSource, Validation};
+/// use roa::preload::*;
+/// use roa::{App, Context};
+/// use serde_json::Value;
+///
+/// async fn end(ctx: &mut Context) -> roa::Result {
+///     match ctx.try_claims::<Value>()? {
+///         Some(claims) => ctx.write(format!("Hello, {}", claims["sub"])),
+///         None => ctx.write("Hello, stranger"),
+///     }
+///     Ok(())
+/// }
 ///
-/// If request fails to pass verification, return 401 UNAUTHORIZED and set response header "WWW-Authenticate".
-#[derive(Debug, Clone, PartialEq)]
+/// let guard = JwtGuard::new(DecodingKey::from_secret(b"123456"), Validation::default())
+///     .sources(vec![
+///         TokenSource::Header,
+///         TokenSource::Cookie("token".to_string()),
+///         TokenSource::Query("access_token".to_string()),
+///     ])
+///     .soft(true);
+/// let app = App::new().gate(guard).end(end);
+/// ```
+#[derive(Debug, Clone)]
 pub struct JwtGuard {
-    secret: DecodingKey<'static>,
+    keys: KeySet,
     validation: Validation,
+    sources: Vec<TokenSource>,
+    soft: bool,
 }
 
 impl JwtGuard {
-    /// Construct guard.
+    /// Construct guard with a single key, which accepts algorithms of the validation.
+    ///
+    /// ### Panics
+    ///
+    /// Panics if algorithms of the validation are not in the same family,
+    /// use `JwtGuard::with_keys` for keys of different types.
     pub fn new(secret: DecodingKey, validation: Validation) -> Self {
+        Self::with_keys(
+            KeySet::with_default(secret, &validation.algorithms),
+            validation,
+        )
+    }
+
+    /// Construct guard with a key set.
+    pub fn with_keys(keys: KeySet, validation: Validation) -> Self {
         Self {
-            secret: secret.into_static(),
+            keys,
             validation,
+            sources: vec![TokenSource::Header],
+            soft: false,
         }
     }
 
-    /// Verify token.
+    /// Set sources to find the token, the first found token is verified.
+    pub fn sources(mut self, sources: impl IntoIterator<Item = TokenSource>) -> Self {
+        self.sources = sources.into_iter().collect();
+        self
+    }
+
+    /// Enable or disable soft mode.
+    ///
+    /// In soft mode, requests without a token pass through as anonymous,
+    /// and no claims are stored. Invalid tokens are still rejected.
+    pub fn soft(mut self, soft: bool) -> Self {
+        self.soft = soft;
+        self
+    }
+
+    /// Get the key set, clones of which can be used to rotate keys.
+    pub fn keys(&self) -> &KeySet {
+        &self.keys
+    }
+
+    /// Find and verify token.
     #[inline]
-    fn verify<S>(&self, ctx: &Context<S>) -> Option<(Bearer, Value)> {
-        let bearer = ctx.req.headers.typed_get::<Authorization<Bearer>>()?.0;
-        let value = decode::<Value>(bearer.token(), &self.secret, &self.validation)
-            .ok()?
-            .claims;
-        Some((bearer, value))
+    fn verify<S>(
+        &self,
+        ctx: &Context<S>,
+    ) -> std::result::Result<(String, DecodingKey<'static>, Value), Rejection> {
+        let token = self
+            .sources
+            .iter()
+            .find_map(|source| source.find(ctx))
+            .ok_or(Rejection::Missing)?;
+        let (secret, value) = verify_by(&self.keys, &token, &self.validation)?;
+        Ok((token, secret, value))
+    }
+}
+
+/// Select a key by `kid` of token, then verify the token by algorithms of the key.
+#[inline]
+fn verify_by<C>(
+    keys: &KeySet,
+    token: &str,
+    validation: &Validation,
+) -> std::result::Result<(DecodingKey<'static>, C), Rejection>
+where
+    C: DeserializeOwned,
+{
+    let header = decode_header(token)?;
+    let entry = keys
+        .select(header.kid.as_deref())
+        .ok_or(Rejection::BadSignature)?;
+    if !entry.algorithms.contains(&header.alg) {
+        return Err(Rejection::Invalid);
     }
+    let validation = Validation {
+        algorithms: entry.algorithms,
+        ..validation.clone()
+    };
+    let claims = decode::<C>(token, &entry.key, &validation)?.claims;
+    Ok((entry.key, claims))
 }
 
 #[async_trait(? Send)]
@@ -174,16 +533,17 @@ impl<'a, S> Middleware<'a, S> for JwtGuard {
     #[inline]
     async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
         match self.verify(ctx) {
-            None => {
-                set_www_authenticate(ctx);
-                throw!(StatusCode::UNAUTHORIZED)
+            Ok((token, secret, value)) => {
+                ctx.store_scoped(JwtScope, "secret", secret);
+                ctx.store_scoped(JwtScope, "token", token);
+                ctx.store_scoped(JwtScope, "value", Some(value));
+                next.await
             }
-            Some((bearer, value)) => {
-                ctx.store_scoped(JwtScope, "secret", self.secret.clone());
-                ctx.store_scoped(JwtScope, "token", bearer);
-                ctx.store_scoped(JwtScope, "value", value);
+            Err(Rejection::Missing) if self.soft => {
+                ctx.store_scoped(JwtScope, "value", None::<Value>);
                 next.await
             }
+            Err(rejection) => Err(reject(ctx, rejection)),
         }
     }
 }
@@ -194,9 +554,25 @@ impl<S> JwtVerifier<S> for Context<S> {
     where
         C: 'static + DeserializeOwned,
     {
-        let value = self.load_scoped::<JwtScope, Value>("value");
-        match value {
-            Some(claims) => Ok(serde_json::from_value((*claims).clone())?),
+        match self.try_claims()? {
+            Some(claims) => Ok(claims),
+            None => Err(Status::new(
+                StatusCode::UNAUTHORIZED,
+                Rejection::Missing.description(),
+                true,
+            )),
+        }
+    }
+
+    #[inline]
+    fn try_claims<C>(&self) -> Result<Option<C>>
+    where
+        C: 'static + DeserializeOwned,
+    {
+        let value = self.load_scoped::<JwtScope, Option<Value>>("value");
+        match value.as_deref() {
+            Some(Some(claims)) => Ok(Some(serde_json::from_value(claims.clone())?)),
+            Some(None) => Ok(None),
             None => Err(guard_not_set()),
         }
     }
@@ -207,28 +583,65 @@ impl<S> JwtVerifier<S> for Context<S> {
         C: 'static + DeserializeOwned,
     {
         let secret = self.load_scoped::<JwtScope, DecodingKey<'static>>("secret");
-        let token = self.load_scoped::<JwtScope, Bearer>("token");
+        let token = self.load_scoped::<JwtScope, String>("token");
         match (secret, token) {
-            (Some(secret), Some(token)) => {
-                match decode(token.token(), &secret, validation) {
-                    Ok(data) => Ok(data.claims),
-                    Err(_) => {
-                        set_www_authenticate(self);
-                        throw!(StatusCode::UNAUTHORIZED)
-                    }
-                }
+            (Some(secret), Some(token)) => match decode(&token, &secret, validation) {
+                Ok(data) => Ok(data.claims),
+                Err(err) => Err(reject(self, err.into())),
+            },
+            _ if self
+                .load_scoped::<JwtScope, Option<Value>>("value")
+                .is_some() =>
+            {
+                Err(reject(self, Rejection::Missing))
             }
             _ => Err(guard_not_set()),
         }
     }
 }
 
+impl<S> JwtIssuer<S> for Context<S> {
+    #[inline]
+    fn issue_token<C>(&self, claims: &C) -> Result<String>
+    where
+        S: TokenIssuer,
+        C: Serialize,
+    {
+        let issuer = self.deref().token_issuer();
+        Ok(encode(&issuer.header, claims, &issuer.key)?)
+    }
+
+    #[inline]
+    fn refresh_token<C, N, F>(
+        &mut self,
+        token: &str,
+        keys: &KeySet,
+        validation: &Validation,
+        f: F,
+    ) -> Result<String>
+    where
+        S: TokenIssuer,
+        C: DeserializeOwned,
+        N: Serialize,
+        F: FnOnce(C) -> Result<N>,
+    {
+        match verify_by(keys, token, validation) {
+            Ok((_, claims)) => self.issue_token(&f(claims)?),
+            Err(rejection) => Err(reject(self, rejection)),
+        }
+    }
+}
+
 #[cfg(all(test, feature = "tcp"))]
 mod tests {
-    use super::{guard, DecodingKey, INVALID_TOKEN};
-    use crate::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+    use super::{
+        guard, Algorithm, DecodingKey, Issuer, JwtGuard, KeySet, Rejection, TokenSource,
+        Validation, INVALID_TOKEN,
+    };
+    use crate::http::header::{AUTHORIZATION, COOKIE, WWW_AUTHENTICATE};
     use crate::http::StatusCode;
     use crate::preload::*;
+    use crate::testing::TestClient;
     use crate::{App, Context};
     use async_std::task::spawn;
     use jsonwebtoken::{encode, EncodingKey, Header};
@@ -289,7 +702,10 @@ mod tests {
             .send()
             .await?;
         assert_eq!(StatusCode::UNAUTHORIZED, resp.status());
-        assert_eq!(&*INVALID_TOKEN, &resp.headers()[WWW_AUTHENTICATE]);
+        assert_eq!(
+            Rejection::Malformed.www_authenticate(),
+            resp.headers()[WWW_AUTHENTICATE]
+        );
 
         // expired token
         let mut user = User {
@@ -317,7 +733,10 @@ mod tests {
             .send()
             .await?;
         assert_eq!(StatusCode::UNAUTHORIZED, resp.status());
-        assert_eq!(&*INVALID_TOKEN, &resp.headers()[WWW_AUTHENTICATE]);
+        assert_eq!(
+            Rejection::Expired.www_authenticate(),
+            resp.headers()[WWW_AUTHENTICATE]
+        );
 
         user.exp = (SystemTime::now() + Duration::from_millis(60))
             .duration_since(UNIX_EPOCH)?
@@ -353,4 +772,419 @@ mod tests {
         assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, resp.status());
         Ok(())
     }
+
+    fn token(kid: Option<&str>, secret: &[u8]) -> crate::Result<String> {
+        let user = User {
+            sub: "user".to_string(),
+            company: "None".to_string(),
+            exp: (SystemTime::now() + Duration::from_secs(60))
+                .duration_since(UNIX_EPOCH)?
+                .as_secs(),
+            id: 0,
+            name: "Hexilee".to_string(),
+        };
+        let header = Header {
+            kid: kid.map(ToString::to_string),
+            ..Header::default()
+        };
+        Ok(encode(&header, &user, &EncodingKey::from_secret(secret))?)
+    }
+
+    async fn name(ctx: &mut Context) -> crate::Result {
+        let user: User = ctx.claims()?;
+        ctx.write(user.name);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn key_rotation() -> crate::Result {
+        let keys = KeySet::from_jwks(
+            r#"{"keys": [
+                {"kty": "oct", "kid": "old", "k": "MTIzNDU2"},
+                {"kty": "oct", "kid": "enc", "use": "enc", "k": "MTIzNDU2"},
+                {"kty": "EC", "kid": "ec", "crv": "P-256"},
+                {"kty": "RSA", "kid": "rsa", "n": "AQAB", "e": "AQAB"}
+            ]}"#,
+        )?;
+        assert!(keys.select(Some("enc")).is_none());
+        assert!(keys.select(Some("ec")).is_none());
+        assert!(keys.select(Some("rsa")).is_some());
+        keys.set_default(DecodingKey::from_secret(b"default"), &[Algorithm::HS256]);
+        let guard = JwtGuard::with_keys(keys.clone(), Validation::default());
+        let client = TestClient::new(&App::new().gate(guard).end(name));
+        let bearer = |token: String| format!("Bearer {}", token);
+
+        let resp = client
+            .get("/")
+            .header(AUTHORIZATION, bearer(token(Some("old"), SECRET)?))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hexilee", resp.text().await?);
+
+        // unknown kid falls back to default key
+        let resp = client
+            .get("/")
+            .header(AUTHORIZATION, bearer(token(Some("new"), b"default")?))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        let resp = client
+            .get("/")
+            .header(AUTHORIZATION, bearer(token(Some("new"), b"654321")?))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNAUTHORIZED, resp.status);
+        assert_eq!(
+            Rejection::BadSignature.www_authenticate(),
+            resp.headers[WWW_AUTHENTICATE]
+        );
+
+        // rotate keys without restart
+        assert_eq!(
+            1,
+            keys.load_jwks(
+                r#"{"keys": [{"kty": "oct", "kid": "new", "k": "NjU0MzIx"}]}"#
+            )?
+        );
+        let resp = client
+            .get("/")
+            .header(AUTHORIZATION, bearer(token(Some("new"), b"654321")?))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        let resp = client
+            .get("/")
+            .header(AUTHORIZATION, bearer(token(Some("old"), SECRET)?))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNAUTHORIZED, resp.status);
+
+        // invalid jwks keeps current keys
+        assert!(keys
+            .load_jwks(r#"{"keys": [{"kty": "oct", "kid": "x"}]}"#)
+            .is_err());
+        assert!(keys.load_jwks(r#"{"key": []}"#).is_err());
+        assert!(keys.select(Some("new")).is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn watch_file() -> crate::Result {
+        let path =
+            std::env::temp_dir().join(format!("roa-jwks-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"keys": []}"#)?;
+        let keys = KeySet::from_jwks_file(&path)?;
+        let watcher = keys.watch_file(&path, Duration::from_millis(10));
+        let handle = spawn(watcher);
+        std::fs::write(
+            &path,
+            r#"{"keys": [{"kty": "oct", "kid": "new", "k": "NjU0MzIx"}]}"#,
+        )?;
+        for _ in 0..100 {
+            if keys.select(Some("new")).is_some() {
+                break;
+            }
+            async_std::task::sleep(Duration::from_millis(10)).await;
+        }
+        handle.cancel().await;
+        std::fs::remove_file(&path)?;
+        assert!(keys.select(Some("new")).is_some());
+        Ok(())
+    }
+
+    /// Modulus of "assets/key.pem" in base64url.
+    const RSA_N: &str = "2WzIA2IpVR9Tb9EFhITlxuhE5rY2a3S6qzYNzQVgSFggxXEPn8k1sQEcer5BfAP986Sck3H0FvB4Bt_I8PwOtUCmhwcc8KtB5TcGPR4fjXnrpC-MIK5UNLkwuyBDKziYzTdBj8kUFX1WxmvEHEgqToPOZfBgsS71cJAR_zOWraDLSRM54jXyvoLZN4Ti9rQagQrvTQ44Vz5ycDQy7UxtbUGh1CVv69vNVr7_SOOh_Nw5FNOZWLWrodGyoec5wh9iqRZgRqiTUc6Lt7V2RWc2X2gjwST2UfI-U46Ip3oaQ7ZD4eAkoqNDxdniBZAykVG3c_99ux4BAESTF8fsNch6UticBxYMuTu-ouvP0psfI9wwwNliJDmACRUTB9AgRynbL1AzhqQoDfsb98IZfjfNOpwnwuLwpMAPhbgd5KNdZaIJ4Hb6_stIyFElOExxd3TAxF2Gshd_lq1JcNHAZ1DSXV5MvOWT_NWgXwbIzUgQ8eIi-HuDYX2UUuaB6R8tbd52H7rbUv6HrfinuSlKWqjSYLkiKHkwUpoMw8y9UycRSzs1E9nPwPTOvRXb0mNCQeBCV9FvStNVXdCUTT8LGPv87xSD2pmt7LijlE6mHLG8McfcWkzA69unCEHIFAFDimTuN7EBljc119xWFTcHMyoZAfFF-oTqwSbBGImruCxnaJE";
+
+    #[tokio::test]
+    async fn rsa_jwks() -> crate::Result {
+        let keys = KeySet::from_jwks(&format!(
+            r#"{{"keys": [
+                {{"kty": "RSA", "kid": "rsa", "alg": "RS256", "n": "{}", "e": "AQAB"}},
+                {{"kty": "oct", "kid": "oct", "k": "MTIzNDU2"}}
+            ]}}"#,
+            RSA_N
+        ))?;
+        let guard = JwtGuard::with_keys(keys, Validation::default());
+        let client = TestClient::new(&App::new().gate(guard).end(name));
+        let user = User {
+            sub: "user".to_string(),
+            company: "None".to_string(),
+            exp: 4102444800,
+            id: 0,
+            name: "Hexilee".to_string(),
+        };
+        let private_key = std::fs::read("../assets/key.pem")?;
+        let header = Header {
+            kid: Some("rsa".to_string()),
+            ..Header::new(Algorithm::RS256)
+        };
+        let token = encode(&header, &user, &EncodingKey::from_rsa_pem(&private_key)?)?;
+        let resp = client
+            .get("/")
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hexilee", resp.text().await?);
+
+        // algorithm mismatches the key.
+        let header = Header {
+            kid: Some("rsa".to_string()),
+            ..Header::default()
+        };
+        let token = encode(&header, &user, &EncodingKey::from_secret(RSA_N.as_bytes()))?;
+        let resp = client
+            .get("/")
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNAUTHORIZED, resp.status);
+        assert_eq!(
+            Rejection::Invalid.www_authenticate(),
+            resp.headers[WWW_AUTHENTICATE]
+        );
+
+        // any algorithm of the key type without "alg".
+        let header = Header {
+            kid: Some("oct".to_string()),
+            ..Header::new(Algorithm::HS512)
+        };
+        let token = encode(&header, &user, &EncodingKey::from_secret(SECRET))?;
+        let resp = client
+            .get("/")
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+
+        // unsupported algorithm
+        assert!(KeySet::from_jwks(
+            r#"{"keys": [{"kty": "oct", "kid": "oct", "alg": "RS256", "k": "MTIzNDU2"}]}"#
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn key_confusion() -> crate::Result {
+        let public_key = std::fs::read("../assets/public.der")?;
+        let keys = KeySet::new();
+        keys.set_default(DecodingKey::from_rsa_der(&public_key), &[Algorithm::RS256]);
+        keys.insert(
+            "rsa",
+            DecodingKey::from_rsa_components(RSA_N, "AQAB"),
+            &[Algorithm::RS256],
+        );
+        let validation = Validation {
+            algorithms: vec![Algorithm::HS256, Algorithm::RS256],
+            ..Validation::default()
+        };
+        let guard = JwtGuard::with_keys(keys, validation);
+        let client = TestClient::new(&App::new().gate(guard).end(name));
+        let user = User {
+            sub: "user".to_string(),
+            company: "None".to_string(),
+            exp: 4102444800,
+            id: 0,
+            name: "Hexilee".to_string(),
+        };
+        let private_key = std::fs::read("../assets/key.pem")?;
+        let token = encode(
+            &Header::new(Algorithm::RS256),
+            &user,
+            &EncodingKey::from_rsa_pem(&private_key)?,
+        )?;
+        let resp = client
+            .get("/")
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+
+        // HS256 token signed with the public key.
+        for kid in &[None, Some("rsa")] {
+            let header = Header {
+                kid: kid.map(ToString::to_string),
+                ..Header::new(Algorithm::HS256)
+            };
+            let token = encode(&header, &user, &EncodingKey::from_secret(&public_key))?;
+            let resp = client
+                .get("/")
+                .header(AUTHORIZATION, format!("Bearer {}", token))
+                .send()
+                .await?;
+            assert_eq!(StatusCode::UNAUTHORIZED, resp.status);
+            assert_eq!(
+                Rejection::Invalid.www_authenticate(),
+                resp.headers[WWW_AUTHENTICATE]
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn mixed_algorithms() {
+        KeySet::new().insert(
+            "key",
+            DecodingKey::from_secret(SECRET),
+            &[Algorithm::HS256, Algorithm::RS256],
+        );
+    }
+
+    #[tokio::test]
+    async fn issue_and_refresh() -> Result<(), Box<dyn std::error::Error>> {
+        async fn issue(ctx: &mut Context<Issuer>) -> crate::Result {
+            let refresh_token = ctx.req.uri.query().unwrap_or("").to_string();
+            let token = if refresh_token.is_empty() {
+                ctx.issue_token(&User {
+                    sub: "user".to_string(),
+                    company: "None".to_string(),
+                    exp: 4102444800,
+                    id: 0,
+                    name: "Hexilee".to_string(),
+                })?
+            } else {
+                let keys = KeySet::with_default(
+                    DecodingKey::from_secret(SECRET),
+                    &[Algorithm::HS256],
+                );
+                ctx.refresh_token(
+                    &refresh_token,
+                    &keys,
+                    &Validation::default(),
+                    |user: User| {
+                        Ok(User {
+                            id: user.id + 1,
+                            ..user
+                        })
+                    },
+                )?
+            };
+            ctx.write(token);
+            Ok(())
+        }
+        let issuer = Issuer::new(EncodingKey::from_secret(SECRET)).kid("current");
+        let client = TestClient::new(&App::state(issuer).end(issue));
+        let token = client.get("/").send().await?.text().await?;
+        let header = jsonwebtoken::decode_header(&token)?;
+        assert_eq!(Some("current"), header.kid.as_deref());
+
+        let resp = client.get(&format!("/?{}", token)).send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        let refreshed = resp.text().await?;
+        let user = jsonwebtoken::decode::<User>(
+            &refreshed,
+            &DecodingKey::from_secret(SECRET),
+            &Validation::default(),
+        )?
+        .claims;
+        assert_eq!(1, user.id);
+
+        let resp = client.get("/?invalid").send().await?;
+        assert_eq!(StatusCode::UNAUTHORIZED, resp.status);
+        assert_eq!(
+            Rejection::Malformed.www_authenticate(),
+            resp.headers[WWW_AUTHENTICATE]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn base64url() {
+        assert_eq!("+/8=", super::keys::base64_standard("-_8"));
+        assert_eq!("MTIzNDU2", super::keys::base64_standard("MTIzNDU2"));
+    }
+
+    #[tokio::test]
+    async fn token_sources() -> crate::Result {
+        let guard =
+            JwtGuard::new(DecodingKey::from_secret(SECRET), Validation::default())
+                .sources(vec![
+                    TokenSource::Query("access_token".to_string()),
+                    TokenSource::Cookie("token".to_string()),
+                    TokenSource::Header,
+                ]);
+        let client = TestClient::new(&App::new().gate(guard).end(name));
+        let valid = token(None, SECRET)?;
+        let invalid = token(None, b"654321")?;
+
+        let resp = client
+            .get(&format!("/?access_token={}", valid))
+            .header(AUTHORIZATION, format!("Bearer {}", invalid))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hexilee", resp.text().await?);
+
+        let resp = client
+            .get("/")
+            .header(COOKIE, format!("theme=dark; token={}", valid))
+            .header(AUTHORIZATION, format!("Bearer {}", invalid))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+
+        // the first found token is verified
+        let resp = client
+            .get(&format!("/?access_token={}", invalid))
+            .header(COOKIE, format!("token={}", valid))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNAUTHORIZED, resp.status);
+        assert_eq!(
+            Rejection::BadSignature.www_authenticate(),
+            resp.headers[WWW_AUTHENTICATE]
+        );
+        assert_eq!("token signature is invalid", resp.text().await?);
+
+        let resp = client.get("/?access_token=").send().await?;
+        assert_eq!(StatusCode::UNAUTHORIZED, resp.status);
+        assert_eq!(&*INVALID_TOKEN, &resp.headers[WWW_AUTHENTICATE]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn soft_mode() -> crate::Result {
+        async fn greet(ctx: &mut Context) -> crate::Result {
+            match ctx.try_claims::<User>()? {
+                Some(user) => ctx.write(user.name),
+                None => {
+                    assert!(ctx.claims::<User>().is_err());
+                    assert!(ctx.verify::<User>(&Validation::default()).is_err());
+                    ctx.write("anonymous")
+                }
+            }
+            Ok(())
+        }
+        let guard =
+            JwtGuard::new(DecodingKey::from_secret(SECRET), Validation::default())
+                .soft(true);
+        let client = TestClient::new(&App::new().gate(guard).end(greet));
+
+        let resp = client.get("/").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("anonymous", resp.text().await?);
+
+        let resp = client
+            .get("/")
+            .header(AUTHORIZATION, format!("Bearer {}", token(None, SECRET)?))
+            .send()
+            .await?;
+        assert_eq!("Hexilee", resp.text().await?);
+
+        let resp = client
+            .get("/")
+            .header(AUTHORIZATION, format!("Bearer {}", token(None, b"654321")?))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNAUTHORIZED, resp.status);
+        assert_eq!(
+            Rejection::BadSignature.www_authenticate(),
+            resp.headers[WWW_AUTHENTICATE]
+        );
+        assert_eq!("token signature is invalid", resp.text().await?);
+        Ok(())
+    }
 }
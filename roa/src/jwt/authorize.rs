@@ -0,0 +1,244 @@
+use super::JwtVerifier;
+use crate::http::StatusCode;
+use crate::{async_trait, Context, Middleware, Next, Result, Status};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// A predicate over claims.
+type Predicate = dyn 'static + Sync + Send + Fn(&Value) -> bool;
+
+/// A middleware to authorize requests by claims.
+/// It must be used in downstream of middleware `JwtGuard`.
+///
+/// If claims fail to satisfy the predicate, return 403 FORBIDDEN with body "permission denied".
+///
+/// Requirements can be combined by `Require::and`, `Require::or`,
+/// or by chaining them as middlewares.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::jwt::{guard, require, require_any_role, require_scope, DecodingKey};
+/// use roa::router::Router;
+/// use roa::{App, Context};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Claims {
+///     sub: String,
+///     level: u8,
+/// }
+///
+/// async fn create(ctx: &mut Context) -> roa::Result {
+///     Ok(())
+/// }
+///
+/// async fn delete(ctx: &mut Context) -> roa::Result {
+///     Ok(())
+/// }
+///
+/// let orders = Router::new()
+///     .gate(require_scope("orders:write"))
+///     .post("/", create)
+///     .gate(
+///         require_any_role(vec!["admin", "operator"])
+///             .or(require(|claims: &Claims| claims.level >= 3)),
+///     )
+///     .delete("/:id", delete);
+/// let router = Router::new().include("/orders", orders);
+/// let app = App::new()
+///     .gate(guard(DecodingKey::from_secret(b"123456")))
+///     .end(router.routes("/").unwrap());
+/// ```
+#[derive(Clone)]
+pub struct Require {
+    predicate: Arc<Predicate>,
+}
+
+impl Require {
+    /// Construct a requirement by a predicate over raw claims.
+    #[inline]
+    fn new(predicate: impl 'static + Sync + Send + Fn(&Value) -> bool) -> Self {
+        Self {
+            predicate: Arc::new(predicate),
+        }
+    }
+
+    /// Satisfied if both requirements are satisfied.
+    pub fn and(self, other: Require) -> Self {
+        Self::new(move |claims| (self.predicate)(claims) && (other.predicate)(claims))
+    }
+
+    /// Satisfied if any of requirements is satisfied.
+    pub fn or(self, other: Require) -> Self {
+        Self::new(move |claims| (self.predicate)(claims) || (other.predicate)(claims))
+    }
+}
+
+/// Require claims to satisfy a custom predicate.
+///
+/// Claims which cannot be deserialized into `C` never satisfy it.
+pub fn require<C, F>(predicate: F) -> Require
+where
+    C: DeserializeOwned,
+    F: 'static + Sync + Send + Fn(&C) -> bool,
+{
+    Require::new(move |claims| match C::deserialize(claims) {
+        Ok(claims) => predicate(&claims),
+        Err(_) => false,
+    })
+}
+
+/// Require a scope in claim "scope" or "scp",
+/// which is either a space-delimited string or an array of strings.
+pub fn require_scope(scope: impl ToString) -> Require {
+    let scope = scope.to_string();
+    Require::new(move |claims| {
+        ["scope", "scp"]
+            .iter()
+            .any(|name| strings(claims, name).any(|value| value == scope))
+    })
+}
+
+/// Require any of roles in claim "roles" or "role",
+/// which is either a space-delimited string or an array of strings.
+pub fn require_any_role<R>(roles: impl IntoIterator<Item = R>) -> Require
+where
+    R: ToString,
+{
+    let roles: Vec<String> = roles.into_iter().map(|role| role.to_string()).collect();
+    Require::new(move |claims| {
+        ["roles", "role"].iter().any(|name| {
+            strings(claims, name).any(|value| roles.iter().any(|role| role == value))
+        })
+    })
+}
+
+/// Strings in a claim.
+#[inline]
+fn strings<'a>(claims: &'a Value, name: &str) -> Box<dyn 'a + Iterator<Item = &'a str>> {
+    match claims.get(name) {
+        Some(Value::String(value)) => Box::new(value.split_whitespace()),
+        Some(Value::Array(values)) => Box::new(values.iter().filter_map(Value::as_str)),
+        _ => Box::new(std::iter::empty()),
+    }
+}
+
+#[async_trait(? Send)]
+impl<'a, S> Middleware<'a, S> for Require {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let claims: Value = ctx.claims()?;
+        if (self.predicate)(&claims) {
+            next.await
+        } else {
+            Err(Status::new(
+                StatusCode::FORBIDDEN,
+                "permission denied",
+                true,
+            ))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tcp", feature = "router"))]
+mod tests {
+    use super::{require, require_any_role, require_scope};
+    use crate::http::header::AUTHORIZATION;
+    use crate::http::StatusCode;
+    use crate::jwt::{guard, DecodingKey};
+    use crate::router::Router;
+    use crate::testing::TestClient;
+    use crate::{App, Context};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+
+    const SECRET: &[u8] = b"123456";
+
+    #[derive(Deserialize)]
+    struct Claims {
+        level: u8,
+    }
+
+    async fn end(_ctx: &mut Context) -> crate::Result {
+        Ok(())
+    }
+
+    fn bearer(claims: Value) -> String {
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(SECRET),
+        )
+        .unwrap();
+        format!("Bearer {}", token)
+    }
+
+    #[tokio::test]
+    async fn authorize() -> Result<(), Box<dyn std::error::Error>> {
+        let router = Router::new()
+            .gate(require_scope("orders:write"))
+            .post("/orders", end)
+            .gate(
+                require_any_role(vec!["admin", "operator"])
+                    .or(require(|claims: &Claims| claims.level >= 3)),
+            )
+            .delete("/orders", end);
+        let app = App::new()
+            .gate(guard(DecodingKey::from_secret(SECRET)))
+            .end(router.routes("/")?);
+        let client = TestClient::new(&app);
+        let exp = 4102444800u64;
+
+        let cases = vec![
+            (
+                json!({"exp": exp, "scope": "orders:read orders:write"}),
+                true,
+                false,
+            ),
+            (
+                json!({"exp": exp, "scp": ["orders:write"], "roles": ["admin"]}),
+                true,
+                true,
+            ),
+            (
+                json!({"exp": exp, "scope": "orders:write", "role": "guest", "level": 3}),
+                true,
+                true,
+            ),
+            (
+                json!({"exp": exp, "scope": "orders:read", "roles": "operator"}),
+                false,
+                false,
+            ),
+            (
+                json!({"exp": exp, "scope": "orders:write", "level": "high"}),
+                true,
+                false,
+            ),
+        ];
+        for (claims, post, delete) in cases {
+            let token = bearer(claims);
+            let resp = client
+                .post("/orders")
+                .header(AUTHORIZATION, &token)
+                .send()
+                .await?;
+            assert_eq!(post, resp.status == StatusCode::OK);
+            if !post {
+                assert_eq!(StatusCode::FORBIDDEN, resp.status);
+                assert_eq!("permission denied", resp.text().await?);
+                continue;
+            }
+            let resp = client
+                .delete("/orders")
+                .header(AUTHORIZATION, &token)
+                .send()
+                .await?;
+            assert_eq!(delete, resp.status == StatusCode::OK);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,310 @@
+use super::{Algorithm, DecodingKey};
+use crate::http::StatusCode;
+use crate::{Result, Status};
+use futures_timer::Delay;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// A shared and reloadable set of decoding keys.
+///
+/// A token is verified by the key whose id equals to the `kid` in its header,
+/// or by the default key if there is no such key.
+///
+/// Each key only accepts tokens signed by its own algorithms.
+/// Algorithms of keys loaded from JWKS are the `alg` of the JWK,
+/// or all algorithms of the `kty` if `alg` is missing.
+/// Algorithms of keys inserted manually must be given explicitly.
+///
+/// All clones share the same keys, so you can keep a clone
+/// to rotate keys of a running `JwtGuard` without restarting.
+///
+/// ### Example
+///
+/// ```rust,no_run
+/// use roa::jwt::{Algorithm, DecodingKey, JwtGuard, KeySet, Validation};
+/// use roa::App;
+/// use async_std::task::spawn;
+/// use std::time::Duration;
+///
+/// # fn main() -> roa::Result<()> {
+/// let keys = KeySet::from_jwks_file("jwks.json")?;
+/// keys.insert("legacy", DecodingKey::from_secret(b"123456"), &[Algorithm::HS256]);
+/// spawn(keys.watch_file("jwks.json", Duration::from_secs(300)));
+/// let app = App::new().gate(JwtGuard::with_keys(keys, Validation::default()));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KeySet {
+    inner: Arc<RwLock<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    default: Option<Entry>,
+    keys: HashMap<String, Entry>,
+}
+
+/// A decoding key and its algorithms.
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    pub key: DecodingKey<'static>,
+    pub algorithms: Vec<Algorithm>,
+}
+
+impl Entry {
+    /// Construct an entry.
+    ///
+    /// ### Panics
+    ///
+    /// Panics if algorithms are empty or not in the same family,
+    /// a key of one type must never be used by algorithms of another type.
+    fn new(key: DecodingKey, algorithms: &[Algorithm]) -> Self {
+        let same_family = match algorithms.first() {
+            Some(&first) => algorithms
+                .iter()
+                .all(|&algorithm| kty(algorithm) == kty(first)),
+            None => false,
+        };
+        assert!(
+            same_family,
+            "algorithms of a key must be non-empty and in the same family, got {:?}",
+            algorithms
+        );
+        Self {
+            key: key.into_static(),
+            algorithms: algorithms.to_vec(),
+        }
+    }
+}
+
+impl KeySet {
+    /// Construct an empty key set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Construct a key set with only a default key, which accepts the algorithms.
+    pub fn with_default(key: DecodingKey, algorithms: &[Algorithm]) -> Self {
+        let keys = Self::new();
+        keys.set_default(key, algorithms);
+        keys
+    }
+
+    /// Construct a key set from a JWKS json document.
+    pub fn from_jwks(jwks: &str) -> Result<Self> {
+        let keys = Self::new();
+        keys.load_jwks(jwks)?;
+        Ok(keys)
+    }
+
+    /// Construct a key set from a JWKS json file.
+    pub fn from_jwks_file(path: impl AsRef<Path>) -> Result<Self> {
+        let keys = Self::new();
+        keys.load_jwks_file(path)?;
+        Ok(keys)
+    }
+
+    /// Set the default key, which is used to verify tokens without a known `kid`.
+    ///
+    /// The key only accepts tokens signed by the algorithms.
+    ///
+    /// ### Panics
+    ///
+    /// Panics if algorithms are empty or not in the same family, like HS256 and RS256.
+    pub fn set_default(&self, key: DecodingKey, algorithms: &[Algorithm]) {
+        self.inner.write().unwrap().default = Some(Entry::new(key, algorithms));
+    }
+
+    /// Insert a key with id.
+    ///
+    /// The key only accepts tokens signed by the algorithms.
+    ///
+    /// ### Panics
+    ///
+    /// Panics if algorithms are empty or not in the same family, like HS256 and RS256.
+    pub fn insert(
+        &self,
+        kid: impl ToString,
+        key: DecodingKey,
+        algorithms: &[Algorithm],
+    ) {
+        self.inner
+            .write()
+            .unwrap()
+            .keys
+            .insert(kid.to_string(), Entry::new(key, algorithms));
+    }
+
+    /// Remove a key by id.
+    pub fn remove(&self, kid: &str) -> Option<DecodingKey<'static>> {
+        Some(self.inner.write().unwrap().keys.remove(kid)?.key)
+    }
+
+    /// Replace all keys with id by keys in a JWKS json document,
+    /// return the number of loaded keys.
+    ///
+    /// Only "RSA" and "oct" keys are supported, others and keys without "kid" are ignored.
+    /// The default key is kept.
+    pub fn load_jwks(&self, jwks: &str) -> Result<usize> {
+        let keys = parse_jwks(jwks)?;
+        let count = keys.len();
+        self.inner.write().unwrap().keys = keys;
+        Ok(count)
+    }
+
+    /// Replace all keys with id by keys in a JWKS json file.
+    pub fn load_jwks_file(&self, path: impl AsRef<Path>) -> Result<usize> {
+        self.load_jwks(&std::fs::read_to_string(path)?)
+    }
+
+    /// Return a future to reload keys by the loader periodically.
+    ///
+    /// Failures are logged and current keys are kept.
+    /// The future never completes, you should spawn it.
+    pub fn reload_every<F, Fut>(
+        &self,
+        interval: Duration,
+        mut loader: F,
+    ) -> impl 'static + Send + Future<Output = ()>
+    where
+        F: 'static + Send + FnMut() -> Fut,
+        Fut: Send + Future<Output = Result<String>>,
+    {
+        let keys = self.clone();
+        async move {
+            loop {
+                Delay::new(interval).await;
+                if let Err(err) = loader().await.and_then(|jwks| keys.load_jwks(&jwks)) {
+                    log::error!("fail to reload jwks: {}", err);
+                }
+            }
+        }
+    }
+
+    /// Return a future to reload keys from a JWKS json file periodically.
+    ///
+    /// The file is read asynchronously, so the executor is never blocked.
+    pub fn watch_file(
+        &self,
+        path: impl Into<PathBuf>,
+        interval: Duration,
+    ) -> impl 'static + Send + Future<Output = ()> {
+        let path = path.into();
+        self.reload_every(interval, move || {
+            let path = path.clone();
+            async move { Ok(async_std::fs::read_to_string(path).await?) }
+        })
+    }
+
+    /// Select a key for a token with the `kid`.
+    pub(crate) fn select(&self, kid: Option<&str>) -> Option<Entry> {
+        let inner = self.inner.read().unwrap();
+        kid.and_then(|kid| inner.keys.get(kid))
+            .or_else(|| inner.default.as_ref())
+            .cloned()
+    }
+}
+
+/// Throw a internal server error with message.
+#[inline]
+fn invalid_jwks(message: &str) -> Status {
+    Status::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("invalid jwks: {}", message),
+        false,
+    )
+}
+
+/// Parse keys with id from a JWKS json document.
+fn parse_jwks(jwks: &str) -> Result<HashMap<String, Entry>> {
+    let document: Value = serde_json::from_str(jwks)?;
+    let jwks = match document.get("keys").and_then(Value::as_array) {
+        Some(keys) => keys,
+        None => return Err(invalid_jwks("field `keys` is missing")),
+    };
+    let mut keys = HashMap::new();
+    for jwk in jwks {
+        let field = |name| jwk.get(name).and_then(Value::as_str);
+        let kid = match field("kid") {
+            Some(kid) if field("use") != Some("enc") => kid,
+            _ => continue,
+        };
+        let key = match (field("kty"), field("n"), field("e"), field("k")) {
+            (Some("RSA"), Some(n), Some(e), _) => {
+                DecodingKey::from_rsa_components(n, e).into_static()
+            }
+            (Some("oct"), _, _, Some(k)) => {
+                DecodingKey::from_base64_secret(&base64_standard(k))?.into_static()
+            }
+            (Some("RSA"), _, _, _) | (Some("oct"), _, _, _) => {
+                return Err(invalid_jwks(&format!("key `{}` is incomplete", kid)))
+            }
+            _ => continue,
+        };
+        let algorithms = match field("alg") {
+            Some(alg) => match alg.parse::<Algorithm>() {
+                Ok(algorithm) if family(field("kty")).contains(&algorithm) => {
+                    vec![algorithm]
+                }
+                _ => {
+                    return Err(invalid_jwks(&format!(
+                        "algorithm of key `{}` is unsupported",
+                        kid
+                    )))
+                }
+            },
+            None => family(field("kty")).to_vec(),
+        };
+        keys.insert(kid.to_string(), Entry { key, algorithms });
+    }
+    Ok(keys)
+}
+
+/// Algorithms of a key type.
+fn family(kty: Option<&str>) -> &'static [Algorithm] {
+    match kty {
+        Some("RSA") => &[
+            Algorithm::RS256,
+            Algorithm::RS384,
+            Algorithm::RS512,
+            Algorithm::PS256,
+            Algorithm::PS384,
+            Algorithm::PS512,
+        ],
+        Some("oct") => &[Algorithm::HS256, Algorithm::HS384, Algorithm::HS512],
+        _ => &[],
+    }
+}
+
+/// Key type of an algorithm.
+fn kty(algorithm: Algorithm) -> &'static str {
+    match algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => "oct",
+        Algorithm::ES256 | Algorithm::ES384 => "EC",
+        _ => "RSA",
+    }
+}
+
+/// Convert unpadded base64url to padded standard base64.
+pub(super) fn base64_standard(value: &str) -> String {
+    let mut value: String = value
+        .chars()
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            c => c,
+        })
+        .collect();
+    let padding = match value.len() % 4 {
+        2 => "==",
+        3 => "=",
+        _ => "",
+    };
+    value.push_str(padding);
+    value
+}
@@ -24,6 +24,14 @@ pub mod websocket;
 #[cfg_attr(feature = "docs", doc(cfg(feature = "cookies")))]
 pub mod cookie;
 
+#[cfg(feature = "session")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "session")))]
+pub mod session;
+
+#[cfg(feature = "csrf")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "csrf")))]
+pub mod csrf;
+
 #[cfg(feature = "jwt")]
 #[cfg_attr(feature = "docs", doc(cfg(feature = "jwt")))]
 pub mod jwt;
@@ -32,18 +40,34 @@ pub mod jwt;
 #[cfg_attr(feature = "docs", doc(cfg(feature = "compress")))]
 pub mod compress;
 
+#[cfg(feature = "tracing")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "tracing")))]
+pub mod tracing;
+
+#[cfg(any(feature = "router", feature = "urlencoded"))]
+mod de;
+
+pub mod auth;
 pub mod body;
+pub mod concurrency;
 pub mod cors;
 pub mod forward;
 pub mod logger;
+pub mod metrics;
 pub mod query;
+pub mod ratelimit;
+pub mod request_id;
 pub mod stream;
+pub mod testing;
+pub mod timeout;
 
 /// Reexport all extension traits.
 pub mod preload {
+    pub use crate::auth::AuthPrincipal;
     pub use crate::body::PowerBody;
     pub use crate::forward::Forward;
     pub use crate::query::Query;
+    pub use crate::request_id::RequestIdGetter;
 
     #[cfg(feature = "tcp")]
     #[doc(no_inline)]
@@ -56,9 +80,21 @@ pub mod preload {
     #[cfg(feature = "cookies")]
     pub use crate::cookie::{CookieGetter, CookieSetter};
 
+    #[cfg(feature = "secure_cookies")]
+    pub use crate::cookie::SecureCookie;
+
+    #[cfg(feature = "session")]
+    pub use crate::session::SessionGetter;
+
+    #[cfg(feature = "csrf")]
+    pub use crate::csrf::CsrfToken;
+
     #[cfg(feature = "jwt")]
-    pub use crate::jwt::JwtVerifier;
+    pub use crate::jwt::{JwtIssuer, JwtVerifier};
 
     #[cfg(feature = "router")]
     pub use crate::router::RouterParam;
+
+    #[cfg(feature = "tracing")]
+    pub use crate::tracing::TraceContextGetter;
 }
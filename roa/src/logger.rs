@@ -1,4 +1,4 @@
-//! This module provides a middleware `logger`.
+//! This module provides a middleware `logger` and a configurable access logger `Logger`.
 //!
 //! ### Example
 //!
@@ -22,24 +22,50 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! ### Access Logger
+//!
+//! ```rust
+//! use roa::logger::{Field, Format, Logger};
+//! use roa::App;
+//!
+//! let app = App::new()
+//!     .gate(
+//!         Logger::new(Format::Json)
+//!             .fields(vec![Field::Time, Field::ClientIp, Field::Uri, Field::Status])
+//!             .skip("/health"),
+//!     )
+//!     .end("Hello, World");
+//! ```
 
+mod format;
+
+pub use format::{Field, Format};
+
+use crate::forward::Forward;
+use crate::http::header::{CONTENT_LENGTH, REFERER, USER_AGENT};
 use crate::http::Uri;
-use crate::{Context, Executor, JoinHandle, Next, Result};
+use crate::request_id::RequestIdGetter;
+use crate::{
+    async_trait, Context, Executor, JoinHandle, Middleware, Next, Result, State,
+};
 use bytes::Bytes;
 use bytesize::ByteSize;
+use format::Record;
 use futures::task::{self, Poll};
 use futures::{Future, Stream};
-use log::{error, info};
+use log::{error, info, log, Level};
 use roa_core::http::{Method, StatusCode};
 use std::io;
 use std::mem;
 use std::pin::Pin;
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 
 /// A finite-state machine to log success information in each successful response.
-enum StreamLogger<S> {
+pub(crate) enum StreamLogger<S, T> {
     /// Polling state, as a body stream.
-    Polling { stream: S, task: LogTask },
+    Polling { stream: S, counter: u64, task: T },
 
     /// Logging state, as a logger future.
     Logging(JoinHandle<()>),
@@ -48,10 +74,15 @@ enum StreamLogger<S> {
     Complete,
 }
 
-/// A task structure to log when polling is complete.
+/// A task to log when polling is complete.
+pub(crate) trait LogTask: Unpin {
+    /// Log with size of response body, return a handle if logging is spawned.
+    fn log(&self, counter: u64) -> Option<JoinHandle<()>>;
+}
+
+/// A task of middleware `logger`.
 #[derive(Clone)]
-struct LogTask {
-    counter: u64,
+struct SimpleTask {
     method: Method,
     status_code: StatusCode,
     uri: Uri,
@@ -59,18 +90,17 @@ struct LogTask {
     exec: Executor,
 }
 
-impl LogTask {
+impl LogTask for SimpleTask {
     #[inline]
-    fn log(&self) -> JoinHandle<()> {
-        let LogTask {
-            counter,
+    fn log(&self, counter: u64) -> Option<JoinHandle<()>> {
+        let SimpleTask {
             method,
             status_code,
             uri,
             start,
             exec,
         } = self.clone();
-        exec.spawn_blocking(move || {
+        Some(exec.spawn_blocking(move || {
             info!(
                 "<-- {} {} {}ms {} {}",
                 method,
@@ -79,13 +109,35 @@ impl LogTask {
                 ByteSize(counter),
                 status_code,
             )
-        })
+        }))
     }
 }
 
-impl<S> Stream for StreamLogger<S>
+/// A task of middleware `Logger`.
+struct AccessTask {
+    record: Record,
+    layout: Arc<Layout>,
+    start: Instant,
+    exec: Executor,
+}
+
+impl LogTask for AccessTask {
+    #[inline]
+    fn log(&self, counter: u64) -> Option<JoinHandle<()>> {
+        let record = Record {
+            latency: self.start.elapsed(),
+            response_size: Some(counter),
+            ..self.record.clone()
+        };
+        let layout = self.layout.clone();
+        Some(self.exec.spawn_blocking(move || layout.log(&record)))
+    }
+}
+
+impl<S, T> Stream for StreamLogger<S, T>
 where
     S: 'static + Send + Send + Unpin + Stream<Item = io::Result<Bytes>>,
+    T: LogTask,
 {
     type Item = io::Result<Bytes>;
 
@@ -94,20 +146,24 @@ where
         cx: &mut task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
         match &mut *self {
-            StreamLogger::Polling { stream, task } => {
-                match futures::ready!(Pin::new(stream).poll_next(cx)) {
-                    Some(Ok(bytes)) => {
-                        task.counter += bytes.len() as u64;
-                        Poll::Ready(Some(Ok(bytes)))
-                    }
-                    None => {
-                        let handler = task.log();
-                        *self = StreamLogger::Logging(handler);
-                        self.poll_next(cx)
-                    }
-                    err => Poll::Ready(err),
+            StreamLogger::Polling {
+                stream,
+                counter,
+                task,
+            } => match futures::ready!(Pin::new(stream).poll_next(cx)) {
+                Some(Ok(bytes)) => {
+                    *counter += bytes.len() as u64;
+                    Poll::Ready(Some(Ok(bytes)))
                 }
-            }
+                None => {
+                    *self = match task.log(*counter) {
+                        Some(handler) => StreamLogger::Logging(handler),
+                        None => StreamLogger::Complete,
+                    };
+                    self.poll_next(cx)
+                }
+                err => Poll::Ready(err),
+            },
 
             StreamLogger::Logging(handler) => {
                 futures::ready!(Pin::new(handler).poll(cx));
@@ -136,15 +192,7 @@ pub async fn logger<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
     match &mut result {
         Err(status) => {
             let status_code = status.status_code;
-            let message = if status.expose {
-                status.message.clone()
-            } else {
-                // set expose to true; then root status_handler won't log this status.
-                status.expose = true;
-
-                // take unexposed message
-                mem::take(&mut status.message)
-            };
+            let message = take_message(status);
             ctx.exec
                 .spawn_blocking(move || {
                     error!("<-- {} {} {}\n{}", method, uri, status_code, message,);
@@ -156,8 +204,8 @@ pub async fn logger<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
             // logging when body polling complete.
             let logger = StreamLogger::Polling {
                 stream: mem::take(&mut ctx.resp.body),
-                task: LogTask {
-                    counter: 0,
+                counter: 0,
+                task: SimpleTask {
                     method,
                     uri,
                     status_code,
@@ -170,3 +218,254 @@ pub async fn logger<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
     }
     result
 }
+
+/// Take message of a failed status.
+#[inline]
+fn take_message(status: &mut crate::Status) -> String {
+    if status.expose {
+        status.message.clone()
+    } else {
+        // set expose to true; then root status_handler won't log this status.
+        status.expose = true;
+
+        // take unexposed message
+        mem::take(&mut status.message)
+    }
+}
+
+/// Format and fields of `Logger`.
+#[derive(Debug)]
+struct Layout {
+    format: Format,
+    fields: Vec<Field>,
+}
+
+impl Layout {
+    /// Log a record, at level `ERROR` for 5xx status, `WARN` for 4xx status,
+    /// or `INFO` for others.
+    fn log(&self, record: &Record) {
+        let level = if record.status.is_server_error() {
+            Level::Error
+        } else if record.status.is_client_error() {
+            Level::Warn
+        } else {
+            Level::Info
+        };
+        log!(level, "{}", record.render(self.format, &self.fields))
+    }
+}
+
+/// A configurable middleware to log one line for each request.
+///
+/// Responses with 5xx status are logged at level `ERROR`, 4xx at `WARN`, others at `INFO`.
+/// Successful responses are logged when body is written completely.
+#[derive(Debug, Clone)]
+pub struct Logger {
+    layout: Arc<Layout>,
+    skip: Vec<String>,
+}
+
+impl Default for Logger {
+    /// Construct a logger in `Format::Combined`.
+    fn default() -> Self {
+        Self::new(Format::Combined)
+    }
+}
+
+impl Logger {
+    /// Construct a logger in a format, with all fields.
+    pub fn new(format: Format) -> Self {
+        Self {
+            layout: Arc::new(Layout {
+                format,
+                fields: Field::ALL.to_vec(),
+            }),
+            skip: Vec::new(),
+        }
+    }
+
+    /// Select fields, used by `Format::Json` and `Format::Text`.
+    pub fn fields(mut self, fields: impl IntoIterator<Item = Field>) -> Self {
+        self.layout = Arc::new(Layout {
+            format: self.layout.format,
+            fields: fields.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Skip requests to a path, e.g. "/health".
+    pub fn skip(mut self, path: impl ToString) -> Self {
+        self.skip.push(path.to_string());
+        self
+    }
+}
+
+#[async_trait(? Send)]
+impl<'a, S: State> Middleware<'a, S> for Logger {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let path = ctx.uri().path();
+        if self.skip.iter().any(|skip| skip == path) {
+            return next.await;
+        }
+        let time = SystemTime::now();
+        let start = Instant::now();
+        let mut result = next.await;
+
+        let header = |name| ctx.get(name).map(ToString::to_string);
+        let mut record = Record {
+            time,
+            client_ip: ctx.client_ip(),
+            method: ctx.method().clone(),
+            uri: ctx.uri().clone(),
+            version: ctx.version(),
+            status: ctx.status(),
+            latency: start.elapsed(),
+            request_size: header(CONTENT_LENGTH).and_then(|size| size.parse().ok()),
+            response_size: None,
+            referer: header(REFERER),
+            user_agent: header(USER_AGENT),
+            error: None,
+            request_id: ctx.request_id().map(|id| id.to_string()),
+        };
+        match &mut result {
+            Err(status) => {
+                let message = take_message(status);
+                record.status = status.status_code;
+                record.response_size = Some(status.message.len() as u64);
+                record.error = Some(message);
+                let layout = self.layout.clone();
+                ctx.exec.spawn_blocking(move || layout.log(&record)).await
+            }
+            Ok(_) => {
+                // logging when body polling complete.
+                let logger = StreamLogger::Polling {
+                    stream: mem::take(&mut ctx.resp.body),
+                    counter: 0,
+                    task: AccessTask {
+                        record,
+                        layout: self.layout.clone(),
+                        start,
+                        exec: ctx.exec.clone(),
+                    },
+                };
+                ctx.resp.write_stream(logger);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{Field, Format, Logger};
+    use crate::http::StatusCode;
+    use crate::testing::TestClient;
+    use crate::{throw, App, Context};
+    use lazy_static::lazy_static;
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+    use std::sync::{Mutex, Once};
+
+    /// A logger capturing lines of module `roa::logger`.
+    struct Capture;
+
+    static CAPTURE: Capture = Capture;
+    static INIT: Once = Once::new();
+
+    lazy_static! {
+        static ref LINES: Mutex<Vec<(Level, String)>> = Mutex::new(Vec::new());
+    }
+
+    impl Log for Capture {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.target() == module_path!().trim_end_matches("::tests")
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                let line = record.args().to_string();
+                LINES.lock().unwrap().push((record.level(), line));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Captured lines containing a path.
+    fn lines(path: &str) -> Vec<(Level, String)> {
+        LINES
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, line)| line.contains(path))
+            .cloned()
+            .collect()
+    }
+
+    /// Install the capturing logger and construct a `Logger`.
+    fn logger() -> Logger {
+        INIT.call_once(|| {
+            log::set_logger(&CAPTURE).unwrap();
+            log::set_max_level(LevelFilter::Info);
+        });
+        Logger::new(Format::Text)
+            .fields(vec![Field::Uri, Field::Status, Field::Error])
+            .skip("/health")
+    }
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        match ctx.uri().path() {
+            "/missing" => throw!(StatusCode::NOT_FOUND),
+            "/crash" => throw!(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "oops\n127.0.0.1 - - forged"
+            ),
+            _ => {
+                ctx.resp.write("Hello, World");
+                Ok(())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn skip() -> Result<(), Box<dyn std::error::Error>> {
+        let client = TestClient::new(&App::new().gate(logger()).end(end));
+        let resp = client.get("/health").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hello, World", resp.text().await?);
+        assert!(lines("/health").is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn levels() -> Result<(), Box<dyn std::error::Error>> {
+        let client = TestClient::new(&App::new().gate(logger()).end(end));
+        let resp = client.get("/ok").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hello, World", resp.text().await?);
+        assert_eq!(vec![(Level::Info, "/ok 200 -".to_string())], lines("/ok"));
+
+        let resp = client.get("/missing").send().await?;
+        assert_eq!(StatusCode::NOT_FOUND, resp.status);
+        assert_eq!(
+            vec![(Level::Warn, "/missing 404 \"\"".to_string())],
+            lines("/missing")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn error() -> Result<(), Box<dyn std::error::Error>> {
+        let client = TestClient::new(&App::new().gate(logger()).end(end));
+        let resp = client.get("/crash").send().await?;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, resp.status);
+        assert_eq!(
+            vec![(
+                Level::Error,
+                "/crash 500 \"oops\\n127.0.0.1 - - forged\"".to_string()
+            )],
+            lines("/crash")
+        );
+        Ok(())
+    }
+}
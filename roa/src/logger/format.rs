@@ -0,0 +1,365 @@
+use crate::http::{Method, StatusCode, Uri, Version};
+use std::fmt::Write;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Format of access logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The Common Log Format, e.g.
+    /// `127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET /index.html HTTP/1.1" 200 2326`.
+    Common,
+
+    /// The Combined Log Format, which is common log format with referer and user agent.
+    ///
+    /// Both preset formats append the quoted error message to the line, if any.
+    Combined,
+
+    /// One JSON object per line, consisting of selected fields.
+    Json,
+
+    /// Selected fields separated by space.
+    Text,
+}
+
+/// A field of access logs, used by `Format::Json` and `Format::Text`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// Time when request is received, in RFC 3339 format and UTC.
+    Time,
+
+    /// Client ip, by `Forward::client_ip`.
+    ClientIp,
+
+    /// Request method.
+    Method,
+
+    /// Request uri.
+    Uri,
+
+    /// HTTP version.
+    Version,
+
+    /// Response status code.
+    Status,
+
+    /// Time to handle request and write response body, in milliseconds.
+    Latency,
+
+    /// Size of request body, by header "Content-Length".
+    RequestSize,
+
+    /// Size of response body.
+    ResponseSize,
+
+    /// Request header "Referer".
+    Referer,
+
+    /// Request header "User-Agent".
+    UserAgent,
+
+    /// Message of failed status.
+    Error,
+
+    /// Request id, by `RequestIdGetter::request_id`.
+    RequestId,
+}
+
+impl Field {
+    /// All fields.
+    pub const ALL: &'static [Field] = &[
+        Field::Time,
+        Field::ClientIp,
+        Field::Method,
+        Field::Uri,
+        Field::Version,
+        Field::Status,
+        Field::Latency,
+        Field::RequestSize,
+        Field::ResponseSize,
+        Field::Referer,
+        Field::UserAgent,
+        Field::Error,
+        Field::RequestId,
+    ];
+
+    /// Name of field, used as key of JSON object.
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Time => "time",
+            Field::ClientIp => "client_ip",
+            Field::Method => "method",
+            Field::Uri => "uri",
+            Field::Version => "version",
+            Field::Status => "status",
+            Field::Latency => "latency_ms",
+            Field::RequestSize => "request_size",
+            Field::ResponseSize => "response_size",
+            Field::Referer => "referer",
+            Field::UserAgent => "user_agent",
+            Field::Error => "error",
+            Field::RequestId => "request_id",
+        }
+    }
+}
+
+/// Value of a field.
+enum Value {
+    Number(u128),
+    Token(String),
+    Quoted(String),
+}
+
+/// Information of a request and its response.
+#[derive(Clone)]
+pub(super) struct Record {
+    pub time: SystemTime,
+    pub client_ip: IpAddr,
+    pub method: Method,
+    pub uri: Uri,
+    pub version: Version,
+    pub status: StatusCode,
+    pub latency: Duration,
+    pub request_size: Option<u64>,
+    pub response_size: Option<u64>,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+    pub error: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl Record {
+    /// Render a log line.
+    pub fn render(&self, format: Format, fields: &[Field]) -> String {
+        match format {
+            Format::Common => {
+                let mut line = self.common();
+                self.append_error(&mut line);
+                line
+            }
+            Format::Combined => {
+                let mut line = self.common();
+                let _ = write!(
+                    line,
+                    " {} {}",
+                    quote(self.referer.as_deref().unwrap_or("-")),
+                    quote(self.user_agent.as_deref().unwrap_or("-")),
+                );
+                self.append_error(&mut line);
+                line
+            }
+            Format::Json => {
+                let mut line = String::from("{");
+                for (index, &field) in fields.iter().enumerate() {
+                    if index > 0 {
+                        line.push(',');
+                    }
+                    line.push_str(&quote(field.name()));
+                    line.push(':');
+                    match self.value(field) {
+                        None => line.push_str("null"),
+                        Some(Value::Number(number)) => {
+                            line.push_str(&number.to_string())
+                        }
+                        Some(Value::Token(value)) | Some(Value::Quoted(value)) => {
+                            line.push_str(&quote(&value))
+                        }
+                    }
+                }
+                line.push('}');
+                line
+            }
+            Format::Text => fields
+                .iter()
+                .map(|&field| match self.value(field) {
+                    None => "-".to_string(),
+                    Some(Value::Number(number)) => number.to_string(),
+                    Some(Value::Token(value)) => value,
+                    Some(Value::Quoted(value)) => quote(&value),
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    /// Render a line in common log format.
+    fn common(&self) -> String {
+        let (year, month, day, hour, minute, second) = utc(self.time);
+        let mut line = format!(
+            "{} - - [{:02}/{}/{}:{:02}:{:02}:{:02} +0000] \"{} {} {:?}\" {} ",
+            self.client_ip,
+            day,
+            MONTHS[month as usize - 1],
+            year,
+            hour,
+            minute,
+            second,
+            self.method,
+            self.uri,
+            self.version,
+            self.status.as_u16(),
+        );
+        match self.response_size {
+            Some(size) if size > 0 => line.push_str(&size.to_string()),
+            _ => line.push('-'),
+        }
+        line
+    }
+
+    /// Append the quoted error message, so that it cannot break the line.
+    fn append_error(&self, line: &mut String) {
+        if let Some(ref error) = self.error {
+            line.push(' ');
+            line.push_str(&quote(error));
+        }
+    }
+
+    /// Value of a field.
+    fn value(&self, field: Field) -> Option<Value> {
+        let value = match field {
+            Field::Time => {
+                let (year, month, day, hour, minute, second) = utc(self.time);
+                Value::Token(format!(
+                    "{}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+                    year, month, day, hour, minute, second
+                ))
+            }
+            Field::ClientIp => Value::Token(self.client_ip.to_string()),
+            Field::Method => Value::Token(self.method.to_string()),
+            Field::Uri => Value::Token(self.uri.to_string()),
+            Field::Version => Value::Token(format!("{:?}", self.version)),
+            Field::Status => Value::Number(self.status.as_u16() as u128),
+            Field::Latency => Value::Number(self.latency.as_millis()),
+            Field::RequestSize => Value::Number(self.request_size? as u128),
+            Field::ResponseSize => Value::Number(self.response_size? as u128),
+            Field::Referer => Value::Quoted(self.referer.clone()?),
+            Field::UserAgent => Value::Quoted(self.user_agent.clone()?),
+            Field::Error => Value::Quoted(self.error.clone()?),
+            Field::RequestId => Value::Token(self.request_id.clone()?),
+        };
+        Some(value)
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Convert time to (year, month, day, hour, minute, second) in UTC.
+fn utc(time: SystemTime) -> (u64, u64, u64, u64, u64, u64) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let (days, rest) = (secs / 86400, secs % 86400);
+
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as u64;
+    (year, month, day, rest / 3600, rest % 3600 / 60, rest % 60)
+}
+
+/// Quote and escape a string, as a JSON string.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Field, Format, Record};
+    use crate::http::{Method, StatusCode, Version};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn record() -> Record {
+        Record {
+            time: UNIX_EPOCH + Duration::from_secs(971_186_136),
+            client_ip: "127.0.0.1".parse().unwrap(),
+            method: Method::GET,
+            uri: "/index.html?a=1".parse().unwrap(),
+            version: Version::HTTP_11,
+            status: StatusCode::OK,
+            latency: Duration::from_millis(12),
+            request_size: None,
+            response_size: Some(2326),
+            referer: Some("http://example.com/".to_string()),
+            user_agent: Some("curl/7.64.1 \"test\"".to_string()),
+            error: None,
+            request_id: None,
+        }
+    }
+
+    #[test]
+    fn presets() {
+        let record = record();
+        assert_eq!(
+            "127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \"GET /index.html?a=1 HTTP/1.1\" 200 2326",
+            record.render(Format::Common, Field::ALL)
+        );
+        assert_eq!(
+            "127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \"GET /index.html?a=1 HTTP/1.1\" 200 2326 \
+             \"http://example.com/\" \"curl/7.64.1 \\\"test\\\"\"",
+            record.render(Format::Combined, Field::ALL)
+        );
+    }
+
+    #[test]
+    fn error() {
+        let mut record = record();
+        record.status = StatusCode::INTERNAL_SERVER_ERROR;
+        record.error = Some("oops\n127.0.0.1 - - \"forged\"".to_string());
+        assert_eq!(
+            "127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \"GET /index.html?a=1 HTTP/1.1\" 500 2326 \
+             \"oops\\n127.0.0.1 - - \\\"forged\\\"\"",
+            record.render(Format::Common, Field::ALL)
+        );
+        assert_eq!(
+            "127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \"GET /index.html?a=1 HTTP/1.1\" 500 2326 \
+             \"http://example.com/\" \"curl/7.64.1 \\\"test\\\"\" \
+             \"oops\\n127.0.0.1 - - \\\"forged\\\"\"",
+            record.render(Format::Combined, Field::ALL)
+        );
+    }
+
+    #[test]
+    fn fields() {
+        let record = record();
+        let fields = &[
+            Field::Time,
+            Field::Status,
+            Field::RequestSize,
+            Field::UserAgent,
+        ];
+        assert_eq!(
+            "{\"time\":\"2000-10-10T13:55:36Z\",\"status\":200,\"request_size\":null,\
+             \"user_agent\":\"curl/7.64.1 \\\"test\\\"\"}",
+            record.render(Format::Json, fields)
+        );
+        assert_eq!(
+            "2000-10-10T13:55:36Z 200 - \"curl/7.64.1 \\\"test\\\"\"",
+            record.render(Format::Text, fields)
+        );
+    }
+}
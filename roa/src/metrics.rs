@@ -0,0 +1,465 @@
+//! This module provides a middleware `Metrics` and an endpoint `Exporter`,
+//! which record request metrics and render them in Prometheus text exposition format.
+//!
+//! Requests are labeled by method (or "OTHER" for extension methods),
+//! status class (like "2xx") and the path pattern of matched route (like "/user/:id/"),
+//! or "unmatched" if no route is matched.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::metrics::Metrics;
+//! use roa::router::Router;
+//! use roa::{App, Context};
+//!
+//! async fn user(ctx: &mut Context) -> roa::Result {
+//!     Ok(())
+//! }
+//!
+//! let metrics = Metrics::new();
+//! let router = Router::new()
+//!     .get("/metrics", metrics.exporter())
+//!     .get("/user/:id", user);
+//! let app = App::new().gate(metrics).end(router.routes("/").unwrap());
+//! ```
+
+use crate::http::header::{HeaderValue, CONTENT_TYPE};
+use crate::http::{Method, StatusCode};
+use crate::logger::{LogTask, StreamLogger};
+use crate::{async_trait, Context, Endpoint, JoinHandle, Middleware, Next, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default buckets of latency, in seconds.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Default buckets of response size, in bytes.
+const SIZE_BUCKETS: &[f64] = &[
+    100.0,
+    1000.0,
+    10_000.0,
+    100_000.0,
+    1_000_000.0,
+    10_000_000.0,
+];
+
+/// Content type of text exposition format.
+const TEXT_FORMAT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Label of requests matching no route.
+const UNMATCHED: &str = "unmatched";
+
+/// Methods labeled as themselves.
+const METHODS: [Method; 9] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::DELETE,
+    Method::HEAD,
+    Method::OPTIONS,
+    Method::PATCH,
+    Method::TRACE,
+    Method::CONNECT,
+];
+
+/// Label of extension methods, which would make unbounded label values.
+const OTHER: &str = "OTHER";
+
+/// Method, status class and route.
+type Labels = (String, &'static str, String);
+
+/// A histogram with cumulative buckets.
+#[derive(Debug, Clone)]
+struct Histogram {
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(buckets: &[f64]) -> Self {
+        Self {
+            counts: vec![0; buckets.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, buckets: &[f64], value: f64) {
+        for (count, &bound) in self.counts.iter_mut().zip(buckets) {
+            if value <= bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// Metrics of requests with the same labels.
+#[derive(Debug, Clone)]
+struct Series {
+    latency: Histogram,
+    size: Histogram,
+}
+
+/// Buckets and series of metrics.
+#[derive(Debug)]
+struct Registry {
+    latency_buckets: Vec<f64>,
+    size_buckets: Vec<f64>,
+    series: Mutex<BTreeMap<Labels, Series>>,
+}
+
+impl Registry {
+    /// Record a request.
+    fn observe(&self, labels: Labels, latency: Duration, size: u64) {
+        let mut series = self.series.lock().unwrap();
+        let series = series.entry(labels).or_insert_with(|| Series {
+            latency: Histogram::new(&self.latency_buckets),
+            size: Histogram::new(&self.size_buckets),
+        });
+        series
+            .latency
+            .observe(&self.latency_buckets, latency.as_secs_f64());
+        series.size.observe(&self.size_buckets, size as f64);
+    }
+}
+
+/// A middleware to record count, latency and response size of requests.
+///
+/// Latency is measured until response body is written completely.
+///
+/// Clones share the same registry.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    registry: Arc<Registry>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    /// Construct a middleware with default buckets.
+    pub fn new() -> Self {
+        Self::with_buckets(LATENCY_BUCKETS.to_vec(), SIZE_BUCKETS.to_vec())
+    }
+
+    /// Construct a middleware with buckets of latency in seconds
+    /// and buckets of response size in bytes, which must not be NaN.
+    pub fn with_buckets(mut latency: Vec<f64>, mut size: Vec<f64>) -> Self {
+        latency.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        size.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Self {
+            registry: Arc::new(Registry {
+                latency_buckets: latency,
+                size_buckets: size,
+                series: Mutex::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    /// Construct an endpoint to render metrics.
+    pub fn exporter(&self) -> Exporter {
+        Exporter(self.clone())
+    }
+
+    /// Render metrics in Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let registry = &self.registry;
+        let series = registry.series.lock().unwrap().clone();
+        let mut text = String::new();
+        write_help(
+            &mut text,
+            "http_requests_total",
+            "counter",
+            "Total number of HTTP requests.",
+        );
+        for (labels, series) in series.iter() {
+            let _ = writeln!(
+                text,
+                "http_requests_total{{{}}} {}",
+                render_labels(labels),
+                series.latency.count
+            );
+        }
+        write_help(
+            &mut text,
+            "http_request_duration_seconds",
+            "histogram",
+            "HTTP request latency in seconds.",
+        );
+        for (labels, series) in series.iter() {
+            write_histogram(
+                &mut text,
+                "http_request_duration_seconds",
+                &render_labels(labels),
+                &registry.latency_buckets,
+                &series.latency,
+            );
+        }
+        write_help(
+            &mut text,
+            "http_response_size_bytes",
+            "histogram",
+            "HTTP response size in bytes.",
+        );
+        for (labels, series) in series.iter() {
+            write_histogram(
+                &mut text,
+                "http_response_size_bytes",
+                &render_labels(labels),
+                &registry.size_buckets,
+                &series.size,
+            );
+        }
+        text
+    }
+}
+
+/// Write "HELP" and "TYPE" lines.
+fn write_help(text: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(text, "# HELP {} {}", name, help);
+    let _ = writeln!(text, "# TYPE {} {}", name, kind);
+}
+
+/// Write lines of a histogram.
+fn write_histogram(
+    text: &mut String,
+    name: &str,
+    labels: &str,
+    buckets: &[f64],
+    histogram: &Histogram,
+) {
+    for (bound, count) in buckets.iter().zip(histogram.counts.iter()) {
+        let _ = writeln!(
+            text,
+            "{}_bucket{{{},le=\"{}\"}} {}",
+            name, labels, bound, count
+        );
+    }
+    let _ = writeln!(
+        text,
+        "{}_bucket{{{},le=\"+Inf\"}} {}",
+        name, labels, histogram.count
+    );
+    let _ = writeln!(text, "{}_sum{{{}}} {}", name, labels, histogram.sum);
+    let _ = writeln!(text, "{}_count{{{}}} {}", name, labels, histogram.count);
+}
+
+/// Render labels, without braces.
+fn render_labels((method, class, route): &Labels) -> String {
+    format!(
+        "method=\"{}\",status=\"{}\",route=\"{}\"",
+        escape(method),
+        class,
+        escape(route)
+    )
+}
+
+/// Escape a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Method label, "OTHER" for extension methods.
+#[inline]
+fn method_label(method: &Method) -> String {
+    if METHODS.contains(method) {
+        method.to_string()
+    } else {
+        OTHER.to_string()
+    }
+}
+
+/// Status class, like "2xx".
+#[inline]
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() / 100 {
+        1 => "1xx",
+        2 => "2xx",
+        3 => "3xx",
+        4 => "4xx",
+        5 => "5xx",
+        _ => "unknown",
+    }
+}
+
+/// A task to record metrics when response body is written completely.
+struct MetricsTask {
+    registry: Arc<Registry>,
+    labels: Labels,
+    start: Instant,
+}
+
+impl LogTask for MetricsTask {
+    #[inline]
+    fn log(&self, counter: u64) -> Option<JoinHandle<()>> {
+        self.registry
+            .observe(self.labels.clone(), self.start.elapsed(), counter);
+        None
+    }
+}
+
+/// Path pattern of matched route.
+#[inline]
+fn matched_route<S>(_ctx: &Context<S>) -> Option<String> {
+    #[cfg(feature = "router")]
+    {
+        use crate::router::RouterParam;
+        _ctx.matched_route().map(|route| route.to_string())
+    }
+    #[cfg(not(feature = "router"))]
+    None
+}
+
+#[async_trait(? Send)]
+impl<'a, S> Middleware<'a, S> for Metrics {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let start = Instant::now();
+        let method = method_label(ctx.method());
+        let result = next.await;
+        let route = matched_route(ctx).unwrap_or_else(|| UNMATCHED.to_string());
+        match &result {
+            Err(status) => {
+                let size = if status.expose {
+                    status.message.len() as u64
+                } else {
+                    0
+                };
+                let labels = (method, status_class(status.status_code), route);
+                self.registry.observe(labels, start.elapsed(), size);
+            }
+            Ok(_) => {
+                let labels = (method, status_class(ctx.status()), route);
+                let logger = StreamLogger::Polling {
+                    stream: mem::take(&mut ctx.resp.body),
+                    counter: 0,
+                    task: MetricsTask {
+                        registry: self.registry.clone(),
+                        labels,
+                        start,
+                    },
+                };
+                ctx.resp.write_stream(logger);
+            }
+        }
+        result
+    }
+}
+
+/// An endpoint to render metrics in Prometheus text exposition format.
+#[derive(Debug, Clone)]
+pub struct Exporter(Metrics);
+
+#[async_trait(? Send)]
+impl<'a, S> Endpoint<'a, S> for Exporter {
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        ctx.resp
+            .headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static(TEXT_FORMAT));
+        ctx.resp.write(self.0.render());
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "tcp", feature = "router"))]
+mod tests {
+    use super::Metrics;
+    use crate::http::header::CONTENT_TYPE;
+    use crate::http::StatusCode;
+    use crate::router::Router;
+    use crate::testing::TestClient;
+    use crate::{throw, App, Context};
+
+    async fn user(ctx: &mut Context) -> crate::Result {
+        ctx.resp.write("Hexilee");
+        Ok(())
+    }
+
+    async fn fail(_ctx: &mut Context) -> crate::Result {
+        throw!(StatusCode::BAD_REQUEST, "bad request")
+    }
+
+    #[tokio::test]
+    async fn metrics() -> Result<(), Box<dyn std::error::Error>> {
+        let metrics = Metrics::with_buckets(vec![10.0], vec![10.0, 5.0]);
+        let router = Router::new()
+            .get("/metrics", metrics.exporter())
+            .get("/user/:id", user)
+            .post("/fail", fail);
+        let app = App::new().gate(metrics.clone()).end(router.routes("/")?);
+        let client = TestClient::new(&app);
+        for id in 0..2 {
+            let resp = client.get(&format!("/user/{}", id)).send().await?;
+            assert_eq!("Hexilee", resp.text().await?);
+        }
+        let resp = client.post("/fail").send().await?;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        let resp = client.get("/not-found").send().await?;
+        assert_eq!(StatusCode::NOT_FOUND, resp.status);
+
+        let resp = client.get("/metrics").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert!(resp.headers[CONTENT_TYPE]
+            .to_str()?
+            .starts_with("text/plain; version=0.0.4"));
+        let text = resp.text().await?;
+        let labels = r#"method="GET",status="2xx",route="/user/:id/""#;
+        for line in vec![
+            "# TYPE http_requests_total counter".to_string(),
+            format!("http_requests_total{{{}}} 2", labels),
+            r#"http_requests_total{method="POST",status="4xx",route="/fail/"} 1"#
+                .to_string(),
+            r#"http_requests_total{method="GET",status="4xx",route="unmatched"} 1"#
+                .to_string(),
+            "# TYPE http_request_duration_seconds histogram".to_string(),
+            format!(
+                "http_request_duration_seconds_bucket{{{},le=\"10\"}} 2",
+                labels
+            ),
+            format!("http_request_duration_seconds_count{{{}}} 2", labels),
+            format!("http_response_size_bytes_bucket{{{},le=\"5\"}} 0", labels),
+            format!("http_response_size_bytes_bucket{{{},le=\"10\"}} 2", labels),
+            format!(
+                "http_response_size_bytes_bucket{{{},le=\"+Inf\"}} 2",
+                labels
+            ),
+            format!("http_response_size_bytes_sum{{{}}} 14", labels),
+        ] {
+            assert!(text.contains(&line), "{} not in\n{}", line, text);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn extension_methods() -> Result<(), Box<dyn std::error::Error>> {
+        let metrics = Metrics::new();
+        let router = Router::new().get("/metrics", metrics.exporter());
+        let app = App::new().gate(metrics.clone()).end(router.routes("/")?);
+        let client = TestClient::new(&app);
+        for method in &["FOO", "BAR", "PROPFIND"] {
+            let resp = client.request(method.parse()?, "/").send().await?;
+            assert_eq!(StatusCode::NOT_FOUND, resp.status);
+        }
+        let text = client.get("/metrics").send().await?.text().await?;
+        assert!(text.contains(
+            r#"http_requests_total{method="OTHER",status="4xx",route="unmatched"} 3"#
+        ));
+        assert!(!text.contains("FOO"));
+        Ok(())
+    }
+}
@@ -31,9 +31,18 @@ use crate::http::StatusCode;
 use crate::{Context, Next, Result, Status, Variable};
 use url::form_urlencoded::parse;
 
+#[cfg(feature = "urlencoded")]
+use crate::de::{from_value, Value};
+
+#[cfg(feature = "urlencoded")]
+use serde::de::DeserializeOwned;
+
 /// A scope to store and load variables in Context::storage.
 struct QueryScope;
 
+/// A scope to store all pairs of query in Context::storage.
+struct PairsScope;
+
 /// A context extension.
 /// This extension must be used in downstream of middleware `query_parser`,
 /// otherwise you cannot get expected query variable.
@@ -132,6 +141,39 @@ pub trait Query {
     /// }
     /// ```
     fn query<'a>(&self, name: &'a str) -> Option<Variable<'a, String>>;
+
+    /// Deserialize query into `T`, throw 400 BAD_REQUEST naming the bad variable if it fails.
+    ///
+    /// Repeated keys like `tag=rust&tag=web` or `tag[]=rust` are deserialized as a sequence,
+    /// keys like `user[name]=Hexilee` are deserialized as a nested struct or map.
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::query::query_parser;
+    /// use roa::{App, Context};
+    /// use roa::preload::*;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Search {
+    ///     keyword: String,
+    ///     tag: Vec<String>,
+    ///     page: Option<u32>,
+    /// }
+    ///
+    /// async fn search(ctx: &mut Context) -> roa::Result {
+    ///     // ?keyword=roa&tag=rust&tag=web
+    ///     let search: Search = ctx.query_as()?;
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().gate(query_parser).end(search);
+    /// ```
+    #[cfg(feature = "urlencoded")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "urlencoded")))]
+    fn query_as<T>(&self) -> Result<T>
+    where
+        T: DeserializeOwned;
 }
 
 /// A middleware to parse query.
@@ -140,9 +182,10 @@ pub async fn query_parser<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
     let query_string = ctx.uri().query().unwrap_or("");
     let pairs: Vec<(String, String)> =
         parse(query_string.as_bytes()).into_owned().collect();
-    for (key, value) in pairs.into_iter() {
-        ctx.store_scoped(QueryScope, key, value);
+    for (key, value) in pairs.iter() {
+        ctx.store_scoped(QueryScope, key.clone(), value.clone());
     }
+    ctx.store_scoped(PairsScope, "pairs", pairs);
     next.await
 }
 
@@ -161,6 +204,20 @@ impl<S> Query for Context<S> {
     fn query<'a>(&self, name: &'a str) -> Option<Variable<'a, String>> {
         self.load_scoped::<QueryScope, String>(name)
     }
+
+    #[cfg(feature = "urlencoded")]
+    #[inline]
+    fn query_as<T>(&self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let value = match self.load_scoped::<PairsScope, Vec<(String, String)>>("pairs")
+        {
+            Some(pairs) => Value::from_pairs(pairs.iter()),
+            None => Value::Map(Vec::new()),
+        };
+        from_value(value)
+    }
 }
 
 #[cfg(all(test, feature = "tcp"))]
@@ -168,9 +225,13 @@ mod tests {
     use crate::http::StatusCode;
     use crate::preload::*;
     use crate::query::query_parser;
+    use crate::testing::TestClient;
     use crate::{App, Context};
     use async_std::task::spawn;
 
+    #[cfg(feature = "urlencoded")]
+    use serde::Deserialize;
+
     #[tokio::test]
     async fn query() -> Result<(), Box<dyn std::error::Error>> {
         async fn test(ctx: &mut Context) -> crate::Result {
@@ -225,4 +286,60 @@ mod tests {
         assert_eq!(StatusCode::OK, resp.status());
         Ok(())
     }
+
+    #[cfg(feature = "urlencoded")]
+    #[tokio::test]
+    async fn query_as() -> Result<(), Box<dyn std::error::Error>> {
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct User {
+            name: String,
+            age: u8,
+        }
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct Search {
+            tag: Vec<String>,
+            page: Option<u32>,
+            ids: Vec<u64>,
+            user: User,
+        }
+
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let search: Search = ctx.query_as()?;
+            assert_eq!(
+                Search {
+                    tag: vec!["rust".to_string(), "web".to_string()],
+                    page: None,
+                    ids: vec![1],
+                    user: User {
+                        name: "Hexilee".to_string(),
+                        age: 20,
+                    },
+                },
+                search
+            );
+            Ok(())
+        }
+        let client = TestClient::new(&App::new().gate(query_parser).end(test));
+        let resp = client
+            .get("/?tag=rust&tag=web&ids[]=1&user[name]=Hexilee&user[age]=20")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+
+        let resp = client
+            .get("/?tag=rust&ids=1&user[name]=Hexilee&user[age]=old")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        assert!(resp
+            .text()
+            .await?
+            .ends_with("type of variable `user[age]` should be u8"));
+
+        let resp = client.get("/?tag=rust&ids=1").send().await?;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        assert_eq!("missing field `user`", resp.text().await?);
+        Ok(())
+    }
 }
@@ -0,0 +1,289 @@
+//! This module provides a middleware `RateLimit`,
+//! which throttles requests by token-bucket or sliding-window algorithm.
+//!
+//! Requests are keyed by `remote_ip`, `client_ip`, `jwt_subject` or a custom function,
+//! and counted in a `RateLimitStore`, which is an in-memory `MemoryStore` by default.
+//!
+//! Headers "RateLimit-Limit", "RateLimit-Remaining" and "RateLimit-Reset" are set on each limited response.
+//! If a request is throttled, return 429 TOO MANY REQUESTS and set header "Retry-After".
+//!
+//! `client_ip` trusts header "X-Forwarded-For", which can be forged by clients to bypass the limit.
+//! Use it only behind a trusted proxy overwriting this header, otherwise use `remote_ip`.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::ratelimit::{client_ip, Policy, RateLimit};
+//! use roa::router::Router;
+//! use roa::{App, Context};
+//! use std::time::Duration;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     Ok(())
+//! }
+//!
+//! let router = Router::new()
+//!     .get("/", end)
+//!     .gate(RateLimit::new(Policy::sliding_window(5, Duration::from_secs(60)), client_ip))
+//!     .post("/login", end);
+//! let app = App::new()
+//!     .gate(RateLimit::new(Policy::token_bucket(100, Duration::from_secs(1)), client_ip))
+//!     .end(router.routes("/").unwrap());
+//! ```
+
+mod memory;
+
+pub use memory::MemoryStore;
+
+use crate::forward::Forward;
+use crate::http::header::{HeaderName, HeaderValue, RETRY_AFTER};
+use crate::http::StatusCode;
+use crate::{async_trait, throw, Context, Middleware, Next, Result, State};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A rate limiting policy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    /// A bucket of `capacity` tokens, refilled fully in `period`.
+    /// Each request takes a token.
+    TokenBucket {
+        /// Capacity of the bucket, which is the max burst.
+        capacity: u64,
+
+        /// Time to refill an empty bucket, must not be zero.
+        period: Duration,
+    },
+
+    /// At most `limit` requests in any `window`,
+    /// approximated by weighting the counter of the previous window.
+    SlidingWindow {
+        /// Max requests in a window.
+        limit: u64,
+
+        /// Size of the window, must not be zero.
+        window: Duration,
+    },
+}
+
+impl Policy {
+    /// Construct a token-bucket policy.
+    pub fn token_bucket(capacity: u64, period: Duration) -> Self {
+        Policy::TokenBucket { capacity, period }
+    }
+
+    /// Construct a sliding-window policy.
+    pub fn sliding_window(limit: u64, window: Duration) -> Self {
+        Policy::SlidingWindow { limit, window }
+    }
+
+    /// Max requests of this policy.
+    #[inline]
+    pub fn limit(&self) -> u64 {
+        match *self {
+            Policy::TokenBucket { capacity, .. } => capacity,
+            Policy::SlidingWindow { limit, .. } => limit,
+        }
+    }
+}
+
+/// The decision of a request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    /// Whether the request is allowed.
+    pub allowed: bool,
+
+    /// Max requests of the policy.
+    pub limit: u64,
+
+    /// Remaining requests.
+    pub remaining: u64,
+
+    /// Time to reset the quota.
+    pub reset: Duration,
+
+    /// Time to retry, if the request is throttled.
+    pub retry_after: Option<Duration>,
+}
+
+/// A storage of rate limiting states, which may be shared by several instances.
+#[async_trait(?Send)]
+pub trait RateLimitStore: 'static + Sync + Send {
+    /// Count a request by key and policy.
+    async fn hit(&self, key: &str, policy: &Policy) -> Result<Decision>;
+}
+
+/// A function to get the key of request, requests without key are not limited.
+pub trait KeyExtractor<S>: 'static + Sync + Send {
+    /// Get the key.
+    fn key(&self, ctx: &Context<S>) -> Option<String>;
+}
+
+impl<S, F> KeyExtractor<S> for F
+where
+    F: 'static + Sync + Send + Fn(&Context<S>) -> Option<String>,
+{
+    #[inline]
+    fn key(&self, ctx: &Context<S>) -> Option<String> {
+        self(ctx)
+    }
+}
+
+/// Key requests by `Forward::client_ip`.
+///
+/// The ip comes from header "X-Forwarded-For" if it's set,
+/// so use it only behind a trusted proxy, or clients can rotate the header to bypass the limit.
+#[inline]
+pub fn client_ip<S: State>(ctx: &Context<S>) -> Option<String> {
+    Some(ctx.client_ip().to_string())
+}
+
+/// Key requests by ip of the peer, ignoring "X-Forwarded-For".
+///
+/// Use it if there is no trusted proxy in front of the app.
+#[inline]
+pub fn remote_ip<S>(ctx: &Context<S>) -> Option<String> {
+    Some(ctx.remote_addr.ip().to_string())
+}
+
+/// Key requests by the subject of json web token,
+/// anonymous requests (in soft mode of `JwtGuard`) are not limited.
+#[cfg(feature = "jwt")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "jwt")))]
+#[inline]
+pub fn jwt_subject<S>(ctx: &Context<S>) -> Option<String> {
+    use crate::jwt::JwtVerifier;
+    let claims = ctx.try_claims::<serde_json::Value>().ok()??;
+    claims.get("sub")?.as_str().map(ToString::to_string)
+}
+
+/// A middleware to throttle requests.
+pub struct RateLimit<K> {
+    policy: Policy,
+    key: K,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl<K> RateLimit<K> {
+    /// Construct a middleware with a policy and a key extractor, counting in a new `MemoryStore`.
+    pub fn new(policy: Policy, key: K) -> Self {
+        Self {
+            policy,
+            key,
+            store: Arc::new(MemoryStore::new()),
+        }
+    }
+
+    /// Use another store.
+    pub fn store(mut self, store: impl RateLimitStore) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+}
+
+/// Duration in seconds, rounded up.
+#[inline]
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + (duration.subsec_nanos() > 0) as u64
+}
+
+#[async_trait(? Send)]
+impl<'a, S, K> Middleware<'a, S> for RateLimit<K>
+where
+    K: KeyExtractor<S>,
+{
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let key = match self.key.key(ctx) {
+            Some(key) => key,
+            None => return next.await,
+        };
+        let decision = self.store.hit(&key, &self.policy).await?;
+        let headers = &mut ctx.resp.headers;
+        let fields = [
+            ("ratelimit-limit", decision.limit),
+            ("ratelimit-remaining", decision.remaining),
+            ("ratelimit-reset", ceil_secs(decision.reset)),
+        ];
+        for &(name, value) in fields.iter() {
+            headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+        }
+        if decision.allowed {
+            return next.await;
+        }
+        if let Some(retry_after) = decision.retry_after {
+            headers.insert(RETRY_AFTER, HeaderValue::from(ceil_secs(retry_after)));
+        }
+        throw!(StatusCode::TOO_MANY_REQUESTS)
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{client_ip, remote_ip, Policy, RateLimit};
+    use crate::http::header::RETRY_AFTER;
+    use crate::http::StatusCode;
+    use crate::testing::TestClient;
+    use crate::{App, Context};
+    use std::time::Duration;
+
+    async fn end(_ctx: &mut Context) -> crate::Result {
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rate_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let policy = Policy::sliding_window(2, Duration::from_secs(60));
+        let app = App::new().gate(RateLimit::new(policy, client_ip)).end(end);
+        let client = TestClient::new(&app);
+        for remaining in (0..2).rev() {
+            let resp = client.get("/").send().await?;
+            assert_eq!(StatusCode::OK, resp.status);
+            assert_eq!("2", resp.headers["ratelimit-limit"]);
+            assert_eq!(remaining.to_string(), resp.headers["ratelimit-remaining"]);
+            assert_eq!("60", resp.headers["ratelimit-reset"]);
+        }
+        let resp = client.get("/").send().await?;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, resp.status);
+        assert_eq!("0", resp.headers["ratelimit-remaining"]);
+        assert_eq!("60", resp.headers[RETRY_AFTER]);
+
+        // requests from another client are counted separately
+        let resp = client
+            .get("/")
+            .header("x-forwarded-for", "192.168.0.1")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn forged_forwarded_for() -> Result<(), Box<dyn std::error::Error>> {
+        let policy = Policy::sliding_window(1, Duration::from_secs(60));
+        let app = App::new().gate(RateLimit::new(policy, remote_ip)).end(end);
+        let client = TestClient::new(&app);
+        let resp = client.get("/").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        let resp = client
+            .get("/")
+            .header("x-forwarded-for", "192.168.0.1")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, resp.status);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn without_key() -> Result<(), Box<dyn std::error::Error>> {
+        let policy = Policy::token_bucket(1, Duration::from_secs(60));
+        let limit = RateLimit::new(policy, |_ctx: &Context| None);
+        let client = TestClient::new(&App::new().gate(limit).end(end));
+        for _ in 0..3 {
+            let resp = client.get("/").send().await?;
+            assert_eq!(StatusCode::OK, resp.status);
+            assert!(resp.headers.get("ratelimit-limit").is_none());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,242 @@
+use super::{Decision, Policy, RateLimitStore};
+use crate::{async_trait, Result};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The state of a key.
+#[derive(Debug, Clone, Copy)]
+enum Entry {
+    Bucket {
+        tokens: f64,
+        last: Instant,
+    },
+    Window {
+        start: Instant,
+        previous: u64,
+        current: u64,
+    },
+}
+
+/// The state and expiration of a key.
+type Entries = HashMap<String, (Entry, Instant)>;
+
+/// An in-memory rate limiting store.
+///
+/// Expired states are swept when the number of keys doubles.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    entries: Entries,
+    sweep_at: usize,
+}
+
+/// Number of keys to trigger the first sweep.
+const MIN_SWEEP: usize = 1024;
+
+impl MemoryStore {
+    /// Construct an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a request at a specific time.
+    fn hit_at(&self, key: &str, policy: &Policy, now: Instant) -> Decision {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.entries.len() >= inner.sweep_at.max(MIN_SWEEP) {
+            inner.entries.retain(|_, (_, expiration)| *expiration > now);
+            inner.sweep_at = inner.entries.len() * 2;
+        }
+        let state = inner.entries.get(key).map(|(entry, _)| *entry);
+        let (entry, expiration, decision) = match *policy {
+            Policy::TokenBucket { capacity, period } => {
+                token_bucket(state, capacity, period, now)
+            }
+            Policy::SlidingWindow { limit, window } => {
+                sliding_window(state, limit, window, now)
+            }
+        };
+        inner.entries.insert(key.to_string(), (entry, expiration));
+        decision
+    }
+}
+
+#[async_trait(?Send)]
+impl RateLimitStore for MemoryStore {
+    #[inline]
+    async fn hit(&self, key: &str, policy: &Policy) -> Result<Decision> {
+        Ok(self.hit_at(key, policy, Instant::now()))
+    }
+}
+
+/// Count a request by token-bucket algorithm.
+fn token_bucket(
+    state: Option<Entry>,
+    capacity: u64,
+    period: Duration,
+    now: Instant,
+) -> (Entry, Instant, Decision) {
+    if capacity == 0 {
+        let decision = Decision {
+            allowed: false,
+            limit: 0,
+            remaining: 0,
+            reset: period,
+            retry_after: None,
+        };
+        let entry = Entry::Bucket {
+            tokens: 0.0,
+            last: now,
+        };
+        return (entry, now, decision);
+    }
+    let capacity_f = capacity as f64;
+    let rate = capacity_f / period.as_secs_f64();
+    let mut tokens = match state {
+        Some(Entry::Bucket { tokens, last }) => {
+            (tokens + (now - last).as_secs_f64() * rate).min(capacity_f)
+        }
+        _ => capacity_f,
+    };
+    let allowed = tokens >= 1.0;
+    let retry_after = if allowed {
+        tokens -= 1.0;
+        None
+    } else {
+        Some(Duration::from_secs_f64((1.0 - tokens) / rate))
+    };
+    let reset = Duration::from_secs_f64((capacity_f - tokens) / rate);
+    let decision = Decision {
+        allowed,
+        limit: capacity,
+        remaining: tokens as u64,
+        reset,
+        retry_after,
+    };
+    (Entry::Bucket { tokens, last: now }, now + reset, decision)
+}
+
+/// Count a request by sliding-window algorithm.
+fn sliding_window(
+    state: Option<Entry>,
+    limit: u64,
+    window: Duration,
+    now: Instant,
+) -> (Entry, Instant, Decision) {
+    let (mut start, mut previous, mut current) = match state {
+        Some(Entry::Window {
+            start,
+            previous,
+            current,
+        }) => (start, previous, current),
+        _ => (now, 0, 0),
+    };
+    let passed = ((now - start).as_secs_f64() / window.as_secs_f64()) as u32;
+    if passed > 0 {
+        previous = if passed == 1 { current } else { 0 };
+        current = 0;
+        start += window * passed;
+    }
+    let elapsed = (now - start).as_secs_f64() / window.as_secs_f64();
+    let estimated = previous as f64 * (1.0 - elapsed) + current as f64;
+    let allowed = estimated + 1.0 <= limit as f64;
+    let reset = start + window - now;
+    let retry_after = if allowed {
+        current += 1;
+        None
+    } else if current >= limit {
+        Some(reset)
+    } else {
+        // the weight of previous window when the request can be allowed.
+        let weight = (limit - current - 1) as f64 / previous as f64;
+        Some(Duration::from_secs_f64(
+            (1.0 - weight - elapsed).max(0.0) * window.as_secs_f64(),
+        ))
+    };
+    let estimated = previous as f64 * (1.0 - elapsed) + current as f64;
+    let decision = Decision {
+        allowed,
+        limit,
+        remaining: (limit as f64 - estimated).max(0.0) as u64,
+        reset,
+        retry_after,
+    };
+    let entry = Entry::Window {
+        start,
+        previous,
+        current,
+    };
+    (entry, start + window * 2, decision)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemoryStore, Policy};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn token_bucket() {
+        let store = MemoryStore::new();
+        let policy = Policy::token_bucket(2, Duration::from_secs(10));
+        let now = Instant::now();
+        let decision = store.hit_at("key", &policy, now);
+        assert!(decision.allowed);
+        assert_eq!(1, decision.remaining);
+        assert_eq!(Duration::from_secs(5), decision.reset);
+        assert!(store.hit_at("key", &policy, now).allowed);
+        let decision = store.hit_at("key", &policy, now);
+        assert!(!decision.allowed);
+        assert_eq!(0, decision.remaining);
+        assert_eq!(Some(Duration::from_secs(5)), decision.retry_after);
+
+        // one token refilled
+        let decision = store.hit_at("key", &policy, now + Duration::from_secs(5));
+        assert!(decision.allowed);
+        assert_eq!(0, decision.remaining);
+        assert!(store.hit_at("other", &policy, now).allowed);
+    }
+
+    #[test]
+    fn sliding_window() {
+        let store = MemoryStore::new();
+        let policy = Policy::sliding_window(4, Duration::from_secs(10));
+        let now = Instant::now();
+        for remaining in (0..4).rev() {
+            let decision = store.hit_at("key", &policy, now);
+            assert!(decision.allowed);
+            assert_eq!(remaining, decision.remaining);
+        }
+        let decision = store.hit_at("key", &policy, now + Duration::from_secs(4));
+        assert!(!decision.allowed);
+        assert_eq!(Some(Duration::from_secs(6)), decision.retry_after);
+
+        // 4 * 0.75 + 0 = 3 requests in last window
+        let decision = store.hit_at("key", &policy, now + Duration::from_millis(12500));
+        assert!(decision.allowed);
+        assert_eq!(0, decision.remaining);
+        let decision = store.hit_at("key", &policy, now + Duration::from_millis(12500));
+        assert!(!decision.allowed);
+        assert_eq!(Some(Duration::from_millis(2500)), decision.retry_after);
+
+        // windows passed
+        let decision = store.hit_at("key", &policy, now + Duration::from_secs(30));
+        assert!(decision.allowed);
+        assert_eq!(3, decision.remaining);
+    }
+
+    #[test]
+    fn sweep() {
+        let store = MemoryStore::new();
+        let policy = Policy::token_bucket(1, Duration::from_secs(1));
+        let now = Instant::now();
+        for i in 0..1024 {
+            store.hit_at(&i.to_string(), &policy, now);
+        }
+        store.hit_at("key", &policy, now + Duration::from_secs(2));
+        assert_eq!(1, store.inner.lock().unwrap().entries.len());
+    }
+}
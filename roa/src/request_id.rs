@@ -0,0 +1,232 @@
+//! This module provides a middleware `RequestId` and a context extension `RequestIdGetter`,
+//! which are used to correlate logs of a request.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::logger::{Field, Format, Logger};
+//! use roa::preload::*;
+//! use roa::request_id::RequestId;
+//! use roa::{App, Context};
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     let id = ctx.request_id().unwrap();
+//!     log::info!("[{}] handling request", id);
+//!     Ok(())
+//! }
+//!
+//! let app = App::new()
+//!     .gate(RequestId::new())
+//!     .gate(Logger::new(Format::Json).fields(vec![Field::RequestId, Field::Status]))
+//!     .end(end);
+//! ```
+
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::{async_trait, Context, Middleware, Next, Result};
+use std::collections::hash_map::RandomState;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A private scope.
+struct RequestIdScope;
+
+/// Max length of a request id from client.
+const MAX_LENGTH: usize = 128;
+
+/// A context extension to get request id.
+pub trait RequestIdGetter {
+    /// Get request id, return None if middleware `RequestId` is not set.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa::preload::*;
+    /// use roa::{Context, Result};
+    ///
+    /// async fn get(ctx: &mut Context) -> Result {
+    ///     if let Some(id) = ctx.request_id() {
+    ///         println!("request id: {}", id);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    fn request_id(&self) -> Option<Arc<String>>;
+}
+
+impl<S> RequestIdGetter for Context<S> {
+    #[inline]
+    fn request_id(&self) -> Option<Arc<String>> {
+        Some(self.load_scoped::<RequestIdScope, String>("id")?.value())
+    }
+}
+
+/// A middleware to read request id from header "X-Request-Id",
+/// or generate one if it's missing or invalid.
+///
+/// The request id is stored in context and echoed in response header,
+/// even if downstream fails.
+#[derive(Clone)]
+pub struct RequestId {
+    header: HeaderName,
+    generator: Arc<dyn 'static + Sync + Send + Fn() -> String>,
+}
+
+impl Debug for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestId")
+            .field("header", &self.header)
+            .finish()
+    }
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestId {
+    /// Construct a middleware using header "X-Request-Id" and generating ULIDs.
+    pub fn new() -> Self {
+        Self {
+            header: HeaderName::from_static("x-request-id"),
+            generator: Arc::new(ulid),
+        }
+    }
+
+    /// Use another header.
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Use another generator, e.g. a UUID generator.
+    pub fn generator(
+        mut self,
+        generator: impl 'static + Sync + Send + Fn() -> String,
+    ) -> Self {
+        self.generator = Arc::new(generator);
+        self
+    }
+}
+
+/// Check if a request id from client is valid.
+#[inline]
+fn valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_LENGTH
+        && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+#[async_trait(? Send)]
+impl<'a, S> Middleware<'a, S> for RequestId {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let id = match ctx.get(&self.header) {
+            Some(id) if valid(id) => id.to_string(),
+            _ => (self.generator)(),
+        };
+        ctx.resp
+            .headers
+            .insert(self.header.clone(), HeaderValue::from_str(&id)?);
+        ctx.store_scoped(RequestIdScope, "id", id);
+        next.await
+    }
+}
+
+/// Crockford's base32 alphabet.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Generate a ULID, consisting of 48-bit timestamp in milliseconds and 80 random bits.
+///
+/// Random bits come from randomly seeded hashers of std, which is not cryptographically secure.
+pub fn ulid() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+    let bits = ((random() as u128) << 64 | random() as u128) & ((1 << 80) - 1);
+    let value = (millis & ((1 << 48) - 1)) << 80 | bits;
+    (0..26)
+        .rev()
+        .map(|index| ALPHABET[(value >> (index * 5)) as usize & 31] as char)
+        .collect()
+}
+
+/// Generate 64 random bits by a randomly seeded hasher of std.
+pub(crate) fn random() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    COUNTER.fetch_add(1, Ordering::Relaxed).hash(&mut hasher);
+    SystemTime::now().hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{ulid, RequestId, RequestIdGetter};
+    use crate::http::StatusCode;
+    use crate::testing::TestClient;
+    use crate::{throw, App, Context};
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        let id = ctx.request_id().unwrap();
+        if id.as_str() == "fail" {
+            throw!(StatusCode::BAD_REQUEST)
+        }
+        ctx.resp.write(id.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn generate() {
+        let (first, second) = (ulid(), ulid());
+        assert_eq!(26, first.len());
+        assert_ne!(first, second);
+        assert!(first.bytes().all(|byte| super::ALPHABET.contains(&byte)));
+    }
+
+    #[tokio::test]
+    async fn request_id() -> Result<(), Box<dyn std::error::Error>> {
+        let client = TestClient::new(&App::new().gate(RequestId::new()).end(end));
+
+        // generated
+        let resp = client.get("/").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        let id = resp.headers["x-request-id"].to_str()?.to_string();
+        assert_eq!(26, id.len());
+        assert_eq!(id, resp.text().await?);
+
+        // from client
+        let resp = client
+            .get("/")
+            .header("x-request-id", "abc-123")
+            .send()
+            .await?;
+        assert_eq!("abc-123", resp.headers["x-request-id"]);
+        assert_eq!("abc-123", resp.text().await?);
+
+        // invalid
+        let resp = client.get("/").header("x-request-id", "a b").send().await?;
+        assert_ne!("a b", resp.headers["x-request-id"]);
+
+        // echoed in error responses
+        let resp = client
+            .get("/")
+            .header("x-request-id", "fail")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        assert_eq!("fail", resp.headers["x-request-id"]);
+
+        // custom generator
+        let app = App::new()
+            .gate(RequestId::new().generator(|| "generated".to_string()))
+            .end(end);
+        let resp = TestClient::new(&app).get("/").send().await?;
+        assert_eq!("generated", resp.text().await?);
+        Ok(())
+    }
+}
@@ -49,7 +49,9 @@
 
 mod endpoints;
 mod err;
+mod methods;
 mod path;
+mod tree;
 
 #[doc(inline)]
 pub use endpoints::*;
@@ -57,21 +59,35 @@ pub use endpoints::*;
 #[doc(inline)]
 pub use err::RouterError;
 
-use crate::http::StatusCode;
+use crate::de::{self, from_value};
+use crate::http::{Method, StatusCode};
 use crate::{
     async_trait, throw, Boxed, Context, Endpoint, EndpointExt, Middleware,
     MiddlewareExt, Result, Shared, Status, Variable,
 };
+use doc_comment::doc_comment;
 use err::Conflict;
-use path::{join_path, standardize_path, Path, RegexPath};
+use path::{erase_variables, join_path, standardize_path, Path};
 use percent_encoding::percent_decode_str;
-use radix_trie::Trie;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
 use std::convert::AsRef;
 use std::result::Result as StdResult;
+use std::sync::Arc;
+use tree::Node;
 
 /// A private scope to store and load variables in Context::storage.
 struct RouterScope;
 
+/// A private scope to store all variables captured by router.
+struct ParamsScope;
+
+/// A private scope to store named paths of route table.
+struct UrlsScope;
+
+/// A private scope to store the matched path pattern.
+struct RouteScope;
+
 /// A context extension.
 /// This extension must be used in `Router`,
 /// otherwise you cannot get expected router parameters.
@@ -137,18 +153,140 @@ pub trait RouterParam {
     ///
     /// ```
     fn param<'a>(&self, name: &'a str) -> Option<Variable<'a, String>>;
+
+    /// Deserialize all router parameters into `T`,
+    /// throw 400 BAD REQUEST naming the bad variable if it fails.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::router::{Router, RouterParam};
+    /// use roa::{App, Context, Status};
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Post {
+    ///     user: String,
+    ///     id: u64,
+    /// }
+    ///
+    /// async fn test(ctx: &mut Context) -> Result<(), Status> {
+    ///     let post: Post = ctx.params()?;
+    ///     assert_eq!("Hexilee", post.user);
+    ///     assert_eq!(0, post.id);
+    ///     Ok(())
+    /// }
+    ///
+    /// let router = Router::new().on("/:user/post/:id", test);
+    /// let app = App::new().end(router.routes("/").unwrap());
+    /// ```
+    fn params<T>(&self) -> Result<T>
+    where
+        T: DeserializeOwned;
+
+    /// Generate url of a named route in current route table, by its variables.
+    /// Throw 500 INTERNAL SERVER ERROR if the route is not found or variables mismatch.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::router::{Router, RouterParam};
+    /// use roa::{App, Context, Status};
+    ///
+    /// async fn user(ctx: &mut Context) -> Result<(), Status> {
+    ///     assert_eq!("/api/user/42", ctx.url_for("user_detail", &[("id", "42")])?);
+    ///     Ok(())
+    /// }
+    ///
+    /// let router = Router::new().get("/user/:id", user).name("user_detail");
+    /// let app = App::new().end(router.routes("/api").unwrap());
+    /// ```
+    fn url_for(&self, name: &str, vars: &[(&str, &str)]) -> Result<String>;
+
+    /// Get the standardized path pattern of matched route, like "/api/user/:id/",
+    /// return `None` if no route is matched.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::router::{Router, RouterParam};
+    /// use roa::{App, Context, Status};
+    ///
+    /// async fn user(ctx: &mut Context) -> Result<(), Status> {
+    ///     assert_eq!("/api/user/:id/", ctx.matched_route().unwrap().as_str());
+    ///     Ok(())
+    /// }
+    ///
+    /// let router = Router::new().get("/user/:id", user);
+    /// let app = App::new().end(router.routes("/api").unwrap());
+    /// ```
+    fn matched_route(&self) -> Option<Arc<String>>;
+}
+
+macro_rules! impl_http_methods {
+    ($end:ident, $method:expr) => {
+        doc_comment! {
+        concat!("Register a new endpoint on ", stringify!($method), ".
+
+You can use it as follow:
+
+```rust
+use roa::{App, Context, Result};
+use roa::router::Router;
+
+async fn foo(ctx: &mut Context) -> Result {
+    Ok(())
+}
+
+let router = Router::new().", stringify!($end), r#"("/foo", foo);
+let app = App::new().end(router.routes("/").unwrap());
+```"#),
+            pub fn $end(
+                self,
+                path: &'static str,
+                endpoint: impl for<'a> Endpoint<'a, S>,
+            ) -> Self {
+                self.route(path, [$method], endpoint)
+            }
+        }
+    };
 }
 
 /// A builder of `RouteTable`.
 pub struct Router<S> {
     middleware: Shared<S>,
-    endpoints: Vec<(String, Boxed<S>)>,
+    endpoints: Vec<Entry<S>>,
+    /// A name given before any endpoint is registered.
+    unbound: Option<&'static str>,
 }
 
-/// An endpoint to route request by uri path.
+/// A registered endpoint.
+struct Entry<S> {
+    path: String,
+    /// `None` means any method.
+    methods: Option<Vec<Method>>,
+    name: Option<&'static str>,
+    endpoint: Boxed<S>,
+}
+
+/// An endpoint to route request by uri path and http method.
+///
+/// If the path matches but the method is not registered on it,
+/// `RouteTable` responds 405 METHOD NOT ALLOWED with an `Allow` header.
+/// OPTIONS requests are answered with the `Allow` header automatically,
+/// and HEAD requests fall back to the GET endpoint.
+///
+/// Routes are matched segment by segment in a tree,
+/// in priority: static > `:variable` > `*{wildcard}`,
+/// no matter the order they are registered in.
+///
+/// Paths differing only in variable names, like `/user/:id` and `/user/:name`,
+/// conflict with each other, because the latter can never be matched.
 pub struct RouteTable<S> {
-    static_route: Trie<String, Boxed<S>>,
-    dynamic_route: Vec<(RegexPath, Boxed<S>)>,
+    root: Node<S>,
+    urls: Arc<HashMap<String, Path>>,
+    /// Registered paths by their erased patterns.
+    shapes: HashMap<String, String>,
 }
 
 impl<S> Router<S>
@@ -160,20 +298,89 @@ where
         Self {
             middleware: ().shared(),
             endpoints: Vec::new(),
+            unbound: None,
         }
     }
 
-    /// Register a new endpoint.
+    /// Register a new endpoint on any method.
     pub fn on(
         mut self,
         path: &'static str,
         endpoint: impl for<'a> Endpoint<'a, S>,
     ) -> Self {
-        self.endpoints
-            .push((path.to_string(), self.register(endpoint)));
+        self.endpoints.push(Entry {
+            path: path.to_string(),
+            methods: None,
+            name: None,
+            endpoint: self.register(endpoint),
+        });
+        self
+    }
+
+    /// Register a new endpoint on methods.
+    ///
+    /// ```rust
+    /// use roa::{App, Context, Result};
+    /// use roa::http::Method;
+    /// use roa::router::Router;
+    ///
+    /// async fn graphql(ctx: &mut Context) -> Result {
+    ///     Ok(())
+    /// }
+    ///
+    /// let router = Router::new().route("/graphql", [Method::GET, Method::POST], graphql);
+    /// let app = App::new().end(router.routes("/").unwrap());
+    /// ```
+    pub fn route(
+        mut self,
+        path: &'static str,
+        methods: impl AsRef<[Method]>,
+        endpoint: impl for<'a> Endpoint<'a, S>,
+    ) -> Self {
+        self.endpoints.push(Entry {
+            path: path.to_string(),
+            methods: Some(methods.as_ref().to_vec()),
+            name: None,
+            endpoint: self.register(endpoint),
+        });
+        self
+    }
+
+    /// Name the last registered endpoint, so its url can be generated by `url_for`.
+    ///
+    /// If no endpoint is registered yet,
+    /// `Router::routes` fails with `RouterError::UnboundName`.
+    ///
+    /// ```rust
+    /// use roa::{App, Context, Result};
+    /// use roa::router::Router;
+    ///
+    /// async fn user(ctx: &mut Context) -> Result {
+    ///     Ok(())
+    /// }
+    ///
+    /// let router = Router::new().get("/user/:id", user).name("user_detail");
+    /// let table = router.routes("/api").unwrap();
+    /// assert_eq!("/api/user/42", table.url_for("user_detail", &[("id", "42")]).unwrap());
+    /// ```
+    pub fn name(mut self, name: &'static str) -> Self {
+        match self.endpoints.last_mut() {
+            Some(entry) => entry.name = Some(name),
+            None => self.unbound = self.unbound.or(Some(name)),
+        }
         self
     }
 
+    impl_http_methods!(get, Method::GET);
+    impl_http_methods!(post, Method::POST);
+    impl_http_methods!(put, Method::PUT);
+    impl_http_methods!(patch, Method::PATCH);
+    impl_http_methods!(options, Method::OPTIONS);
+    impl_http_methods!(delete, Method::DELETE);
+    impl_http_methods!(head, Method::HEAD);
+    impl_http_methods!(trace, Method::TRACE);
+    impl_http_methods!(connect, Method::CONNECT);
+
     /// Chain an endpoint to Router::middleware.
     fn register(&self, endpoint: impl for<'a> Endpoint<'a, S>) -> Boxed<S> {
         self.middleware.clone().end(endpoint).boxed()
@@ -181,10 +388,14 @@ where
 
     /// Include another router with prefix.
     pub fn include(mut self, prefix: &'static str, router: Router<S>) -> Self {
-        for (path, endpoint) in router.endpoints {
-            self.endpoints
-                .push((join_path([prefix, path.as_str()]), self.register(endpoint)))
+        for entry in router.endpoints {
+            self.endpoints.push(Entry {
+                path: join_path([prefix, entry.path.as_str()]),
+                endpoint: self.register(entry.endpoint),
+                ..entry
+            })
         }
+        self.unbound = self.unbound.or(router.unbound);
         self
     }
 
@@ -193,19 +404,36 @@ where
         let Self {
             middleware,
             endpoints,
+            unbound,
         } = self;
         Self {
             middleware: middleware.chain(next).shared(),
             endpoints,
+            unbound,
         }
     }
 
     /// Build RouteTable with path prefix.
     pub fn routes(self, prefix: &'static str) -> StdResult<RouteTable<S>, RouterError> {
+        if let Some(name) = self.unbound {
+            return Err(RouterError::UnboundName(name.to_string()));
+        }
         let mut route_table = RouteTable::default();
-        for (raw_path, endpoint) in self.endpoints {
-            route_table.insert(join_path([prefix, raw_path.as_str()]), endpoint)?;
+        let mut urls = HashMap::new();
+        for entry in self.endpoints {
+            let path = standardize_path(&join_path([prefix, entry.path.as_str()]));
+            if let Some(name) = entry.name {
+                if urls.insert(name.to_string(), path.parse()?).is_some() {
+                    return Err(Conflict::Name(name.to_string()).into());
+                }
+            }
+            route_table.insert(
+                path,
+                entry.methods.as_ref().map(AsRef::as_ref),
+                entry.endpoint,
+            )?;
         }
+        route_table.urls = Arc::new(urls);
         Ok(route_table)
     }
 }
@@ -216,29 +444,56 @@ where
 {
     fn new() -> Self {
         Self {
-            static_route: Trie::new(),
-            dynamic_route: Vec::new(),
+            root: Node::new(),
+            urls: Arc::new(HashMap::new()),
+            shapes: HashMap::new(),
         }
     }
 
-    /// Insert endpoint to table.
+    /// Generate url of a named route by its variables.
+    /// Throw 500 INTERNAL SERVER ERROR if the route is not found or variables mismatch.
+    pub fn url_for(&self, name: &str, vars: &[(&str, &str)]) -> Result<String> {
+        url_for(&self.urls, name, vars)
+    }
+
+    /// Insert endpoint to table, `None` methods means any method.
     fn insert(
         &mut self,
         raw_path: impl AsRef<str>,
+        methods: Option<&[Method]>,
         endpoint: Boxed<S>,
     ) -> StdResult<(), RouterError> {
-        match raw_path.as_ref().parse()? {
-            Path::Static(path) => {
-                if self.static_route.insert(path.clone(), endpoint).is_some() {
-                    return Err(Conflict::Path(path).into());
+        let raw = standardize_path(raw_path.as_ref());
+        let shape = erase_variables(&raw);
+        if let Some(existing) = self.shapes.get(&shape) {
+            if *existing != raw {
+                return Err(Conflict::Variable {
+                    var_name: conflict_variable(existing, &raw),
+                    paths: (existing.clone(), raw),
                 }
+                .into());
             }
-            Path::Dynamic(regex_path) => self.dynamic_route.push((regex_path, endpoint)),
         }
+        self.root.insert(&raw, methods, endpoint)?;
+        self.shapes.insert(shape, raw);
         Ok(())
     }
 }
 
+/// The first variable of `raw` not named in `existing`.
+fn conflict_variable(existing: &str, raw: &str) -> String {
+    let vars = |path: &str| match path.parse() {
+        Ok(Path::Dynamic(regex_path)) => regex_path.vars,
+        _ => HashSet::new(),
+    };
+    let (existing, raw) = (vars(existing), vars(raw));
+    raw.difference(&existing)
+        .min()
+        .or_else(|| raw.iter().min())
+        .cloned()
+        .unwrap_or_default()
+}
+
 impl<S> Default for Router<S>
 where
     S: 'static,
@@ -281,23 +536,15 @@ where
                 },
             )?);
 
-        // search static routes
-        if let Some(end) = self.static_route.get(&path) {
-            return end.call(ctx).await;
-        }
-
-        // search dynamic routes
-        for (regexp_path, end) in self.dynamic_route.iter() {
-            if let Some(cap) = regexp_path.re.captures(&path) {
-                for var in regexp_path.vars.iter() {
-                    ctx.store_scoped(
-                        RouterScope,
-                        var.to_string(),
-                        cap[var.as_str()].to_string(),
-                    );
-                }
-                return end.call(ctx).await;
+        let mut vars = Vec::new();
+        if let Some(route) = self.root.find(&path, &mut vars) {
+            for (name, value) in vars.iter() {
+                ctx.store_scoped(RouterScope, name.clone(), value.clone());
             }
+            ctx.store_scoped(ParamsScope, "params", vars);
+            ctx.store_scoped(UrlsScope, "urls", self.urls.clone());
+            ctx.store_scoped(RouteScope, "route", route.raw.clone());
+            return route.methods.call(ctx).await;
         }
 
         // 404 NOT FOUND
@@ -320,17 +567,84 @@ impl<S> RouterParam for Context<S> {
     fn param<'a>(&self, name: &'a str) -> Option<Variable<'a, String>> {
         self.load_scoped::<RouterScope, String>(name)
     }
+    #[inline]
+    fn params<T>(&self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let vars = match self.load_scoped::<ParamsScope, Vec<(String, String)>>("params")
+        {
+            Some(vars) => vars
+                .iter()
+                .map(|(name, value)| (name.clone(), de::Value::Str(value.clone())))
+                .collect(),
+            None => Vec::new(),
+        };
+        from_value(de::Value::Map(vars))
+    }
+    #[inline]
+    fn url_for(&self, name: &str, vars: &[(&str, &str)]) -> Result<String> {
+        match self.load_scoped::<UrlsScope, Arc<HashMap<String, Path>>>("urls") {
+            Some(urls) => url_for(&urls, name, vars),
+            None => url_for(&HashMap::new(), name, vars),
+        }
+    }
+    #[inline]
+    fn matched_route(&self) -> Option<Arc<String>> {
+        Some(self.load_scoped::<RouteScope, String>("route")?.value())
+    }
+}
+
+/// Generate url of a named route, validating variables.
+fn url_for(
+    urls: &HashMap<String, Path>,
+    name: &str,
+    vars: &[(&str, &str)],
+) -> Result<String> {
+    let path = urls.get(name).ok_or_else(|| {
+        Status::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("route `{}` is not found", name),
+            false,
+        )
+    })?;
+    let vars: HashMap<&str, &str> = vars.iter().cloned().collect();
+    let expected = match path {
+        Path::Static(_) => HashSet::new(),
+        Path::Dynamic(regex_path) => {
+            regex_path.vars.iter().map(String::as_str).collect()
+        }
+    };
+    if let Some(var) = expected.iter().find(|var| !vars.contains_key(*var)) {
+        throw!(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("variable `{}` of route `{}` is required", var, name),
+            false
+        )
+    }
+    if let Some(var) = vars.keys().find(|var| !expected.contains(*var)) {
+        throw!(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("route `{}` has no variable `{}`", name, var),
+            false
+        )
+    }
+    Ok(path.fill(&vars))
 }
 
 #[cfg(all(test, feature = "tcp"))]
 mod tests {
-    use super::Router;
-    use crate::http::StatusCode;
+    use super::{Router, RouterParam};
+    use crate::body::PowerBody;
+    use crate::http::header::ALLOW;
+    use crate::http::{Method, StatusCode};
     use crate::tcp::Listener;
+    use crate::testing::TestClient;
     use crate::{App, Context, Next, Status};
     use async_std::task::spawn;
     use encoding::EncoderTrap;
     use percent_encoding::NON_ALPHANUMERIC;
+    use serde::Deserialize;
 
     async fn gate(ctx: &mut Context, next: Next<'_>) -> Result<(), Status> {
         ctx.store("id", "0".to_string());
@@ -377,6 +691,222 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn conflict_method() -> Result<(), Box<dyn std::error::Error>> {
+        let router = Router::new().get("/", test).route("/", [Method::GET], test);
+        assert!(router.routes("/").is_err());
+        let router = Router::new().on("/:id", test).post("/:id", test);
+        assert!(router.routes("/").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn route_by_method() -> Result<(), Box<dyn std::error::Error>> {
+        async fn create(ctx: &mut Context) -> Result<(), Status> {
+            ctx.resp.status = StatusCode::CREATED;
+            Ok(())
+        }
+        let router = Router::new()
+            .get("/user/:id", test)
+            .post("/user/:id", create)
+            .route("/", [Method::PUT, Method::DELETE], test);
+        let client = TestClient::new(&App::new().gate(gate).end(router.routes("/")?));
+
+        let resp = client.post("/user/0").send().await?;
+        assert_eq!(StatusCode::CREATED, resp.status);
+        let resp = client.head("/user/0").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+
+        let resp = client.patch("/user/0").send().await?;
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, resp.status);
+        assert_eq!("GET, POST, OPTIONS, HEAD", resp.headers[ALLOW]);
+        assert_eq!("Method PATCH not allowed", resp.text().await?);
+
+        let resp = client.options("/").send().await?;
+        assert_eq!(StatusCode::NO_CONTENT, resp.status);
+        assert_eq!("PUT, OPTIONS, DELETE", resp.headers[ALLOW]);
+        Ok(())
+    }
+
+    #[test]
+    fn conflict_variable() -> Result<(), Box<dyn std::error::Error>> {
+        let router = Router::new().on("/user/:id", test).on("/user/:name", test);
+        let err = router.routes("/").err().unwrap();
+        assert_eq!(
+            "Conflict! conflict variable `name`: between `/user/:id/` and `/user/:name/`",
+            err.to_string()
+        );
+        let router = Router::new()
+            .on("/file/*{path}", test)
+            .on("/file/*{name}", test);
+        let err = router.routes("/").err().unwrap();
+        assert_eq!(
+            "Conflict! conflict variable `name`: between `/file/*{path}/` and `/file/*{name}/`",
+            err.to_string()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn route_priority() -> Result<(), Box<dyn std::error::Error>> {
+        async fn endpoint(ctx: &mut Context) -> Result<(), Status> {
+            let mut vars = Vec::new();
+            for name in &["path", "id", "dir", "file"] {
+                if let Some(value) = ctx.param(name) {
+                    vars.push(format!("{}={}", name, value.as_str()));
+                }
+            }
+            ctx.write(vars.join("&"));
+            Ok(())
+        }
+        let router = Router::new()
+            .on("/user/*{path}", endpoint)
+            .on("/user/:id", endpoint)
+            .on("/user/:id/profile", endpoint)
+            .on("/user/me", endpoint)
+            .on("/usr/include/*{dir}/*{file}.h", endpoint);
+        let client = TestClient::new(&App::new().end(router.routes("/")?));
+        for (path, expected) in &[
+            ("/user/me", ""),
+            ("/user/0", "id=0"),
+            ("/user/me/profile", "id=me"),
+            ("/user/0/post/1", "path=0/post/1"),
+            ("/usr/include/uv/uv.h", "dir=uv&file=uv"),
+        ] {
+            let resp = client.get(path).send().await?;
+            assert_eq!(StatusCode::OK, resp.status);
+            assert_eq!(*expected, resp.text().await?);
+        }
+        let resp = client.get("/user").send().await?;
+        assert_eq!(StatusCode::NOT_FOUND, resp.status);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn variable_names() -> Result<(), Box<dyn std::error::Error>> {
+        async fn endpoint(ctx: &mut Context) -> Result<(), Status> {
+            let mut vars = Vec::new();
+            for name in &["id", "name"] {
+                if let Some(value) = ctx.param(name) {
+                    vars.push(format!("{}={}", name, value.as_str()));
+                }
+            }
+            ctx.write(vars.join("&"));
+            Ok(())
+        }
+        let router = Router::new()
+            .on("/user/:id", endpoint)
+            .on("/user/:name/post", endpoint);
+        let client = TestClient::new(&App::new().end(router.routes("/")?));
+        for (path, expected) in
+            &[("/user/0", "id=0"), ("/user/Hexilee/post", "name=Hexilee")]
+        {
+            let resp = client.get(path).send().await?;
+            assert_eq!(StatusCode::OK, resp.status);
+            assert_eq!(*expected, resp.text().await?);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn typed_params() -> Result<(), Box<dyn std::error::Error>> {
+        #[derive(Deserialize)]
+        struct Post {
+            user: String,
+            id: u64,
+            draft: Option<bool>,
+        }
+        async fn endpoint(ctx: &mut Context) -> Result<(), Status> {
+            let post: Post = ctx.params()?;
+            ctx.write(format!("{}:{}:{:?}", post.user, post.id, post.draft));
+            Ok(())
+        }
+        let router = Router::new().on("/:user/post/:id", endpoint);
+        let client = TestClient::new(&App::new().end(router.routes("/")?));
+        let resp = client.get("/Hexilee/post/1").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hexilee:1:None", resp.text().await?);
+
+        let resp = client.get("/Hexilee/post/one").send().await?;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        assert!(resp
+            .text()
+            .await?
+            .ends_with("type of variable `id` should be u64"));
+        Ok(())
+    }
+
+    #[test]
+    fn url_for() -> Result<(), Box<dyn std::error::Error>> {
+        let user_router = Router::new()
+            .get("/", test)
+            .name("user_list")
+            .get("/:id", test)
+            .name("user_detail");
+        let router = Router::new()
+            .on("/static/*{path}", test)
+            .name("static")
+            .include("/user", user_router);
+        let table = router.routes("/api")?;
+        assert_eq!("/api/user", table.url_for("user_list", &[]).unwrap());
+        assert_eq!(
+            "/api/user/42",
+            table.url_for("user_detail", &[("id", "42")]).unwrap()
+        );
+        assert_eq!(
+            "/api/user/a%2Fb%20c",
+            table.url_for("user_detail", &[("id", "a/b c")]).unwrap()
+        );
+        assert_eq!(
+            "/api/static/css/index.css",
+            table
+                .url_for("static", &[("path", "css/index.css")])
+                .unwrap()
+        );
+        assert!(table.url_for("user", &[]).is_err());
+        assert!(table.url_for("user_detail", &[]).is_err());
+        assert!(table
+            .url_for("user_detail", &[("id", "42"), ("name", "Hexilee")])
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn conflict_name() {
+        let router = Router::new()
+            .get("/user", test)
+            .name("user")
+            .post("/user", test)
+            .name("user");
+        let err = router.routes("/").err().unwrap();
+        assert_eq!("Conflict! conflict name: `user`", err.to_string());
+    }
+
+    #[test]
+    fn unbound_name() {
+        let router = Router::new().name("user").get("/user", test);
+        let err = router.routes("/").err().unwrap();
+        assert_eq!(
+            "name `user` is given before any endpoint is registered",
+            err.to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn context_url_for() -> Result<(), Box<dyn std::error::Error>> {
+        async fn endpoint(ctx: &mut Context) -> Result<(), Status> {
+            let url = ctx.url_for("post", &[("id", "1")])?;
+            ctx.write(url);
+            Ok(())
+        }
+        let router = Router::new().get("/post/:id", endpoint).name("post");
+        let client = TestClient::new(&App::new().end(router.routes("/blog")?));
+        let resp = client.get("/blog/post/0").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("/blog/post/1", resp.text().await?);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn route_not_found() -> Result<(), Box<dyn std::error::Error>> {
         let app = App::new().end(Router::default().routes("/")?);
@@ -1,14 +1,41 @@
 mod dispatcher;
 mod guard;
 
+use crate::http::header::{HeaderValue, ALLOW};
 use crate::http::{Method, StatusCode};
-use crate::{throw, Result};
+use crate::{throw, Context, Result};
 
+/// Methods known by router.
+const ALL_METHODS: [Method; 9] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::OPTIONS,
+    Method::DELETE,
+    Method::HEAD,
+    Method::TRACE,
+    Method::CONNECT,
+];
+
+/// Join allowed methods in a stable order, as the value of `Allow` header.
+pub(super) fn allow_value(allowed: impl Fn(&Method) -> bool) -> HeaderValue {
+    let methods: Vec<&str> = ALL_METHODS
+        .iter()
+        .filter(|method| allowed(method))
+        .map(Method::as_str)
+        .collect();
+    // methods are all visible ascii, join of them must be a valid header value.
+    HeaderValue::from_str(&methods.join(", ")).unwrap()
+}
+
+/// Throw 405 METHOD NOT ALLOWED with `Allow` header.
 #[inline]
-fn method_not_allowed(method: &Method) -> Result {
+pub(super) fn method_not_allowed<S>(ctx: &mut Context<S>, allow: HeaderValue) -> Result {
+    ctx.resp.headers.insert(ALLOW, allow);
     throw!(
         StatusCode::METHOD_NOT_ALLOWED,
-        format!("Method {} not allowed", method)
+        format!("Method {} not allowed", ctx.method())
     )
 }
 
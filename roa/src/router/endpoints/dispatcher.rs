@@ -1,4 +1,4 @@
-use super::method_not_allowed;
+use super::{allow_value, method_not_allowed};
 use crate::http::Method;
 use crate::{async_trait, Context, Endpoint, Result};
 use doc_comment::doc_comment;
@@ -98,7 +98,10 @@ where
     async fn call(&'a self, ctx: &'a mut Context<S>) -> Result<()> {
         match self.0.get(ctx.method()) {
             Some(endpoint) => endpoint.call(ctx).await,
-            None => method_not_allowed(ctx.method()),
+            None => method_not_allowed(
+                ctx,
+                allow_value(|method| self.0.contains_key(method)),
+            ),
         }
     }
 }
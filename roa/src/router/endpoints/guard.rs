@@ -1,22 +1,9 @@
-use super::method_not_allowed;
+use super::{allow_value, method_not_allowed, ALL_METHODS};
 use crate::http::Method;
 use crate::{async_trait, Context, Endpoint, Result};
 use std::collections::HashSet;
 use std::iter::FromIterator;
 
-/// Methods allowed in `Guard`.
-const ALL_METHODS: [Method; 9] = [
-    Method::GET,
-    Method::POST,
-    Method::PUT,
-    Method::PATCH,
-    Method::OPTIONS,
-    Method::DELETE,
-    Method::HEAD,
-    Method::TRACE,
-    Method::CONNECT,
-];
-
 /// An endpoint wrapper to guard endpoint by http method.
 pub struct Guard<E> {
     white_list: HashSet<Method>,
@@ -84,7 +71,10 @@ where
         if self.white_list.contains(ctx.method()) {
             self.endpoint.call(ctx).await
         } else {
-            method_not_allowed(ctx.method())
+            method_not_allowed(
+                ctx,
+                allow_value(|method| self.white_list.contains(method)),
+            )
         }
     }
 }
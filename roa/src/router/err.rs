@@ -9,12 +9,16 @@ pub enum RouterError {
 
     /// Variables, methods or paths conflict.
     Conflict(Conflict),
+
+    /// A name is given by `Router::name` before any endpoint is registered.
+    UnboundName(String),
 }
 
 /// Router conflict.
 #[derive(Debug, Eq, PartialEq)]
 pub enum Conflict {
     Path(String),
+    Name(String),
     Method(String, http::Method),
     Variable {
         paths: (String, String),
@@ -26,6 +30,7 @@ impl Display for Conflict {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
             Conflict::Path(path) => f.write_str(&format!("conflict path: `{}`", path)),
+            Conflict::Name(name) => f.write_str(&format!("conflict name: `{}`", name)),
             Conflict::Method(path, method) => f.write_str(&format!(
                 "conflict method: `{}` on `{}` is already set",
                 method, path
@@ -47,6 +52,10 @@ impl Display for RouterError {
             RouterError::MissingVariable(path) => {
                 f.write_str(&format!("missing variable on path {}", path))
             }
+            RouterError::UnboundName(name) => f.write_str(&format!(
+                "name `{}` is given before any endpoint is registered",
+                name
+            )),
         }
     }
 }
@@ -71,6 +80,10 @@ mod tests {
             "conflict path: `/`",
             Conflict::Path("/".to_string()).to_string()
         );
+        assert_eq!(
+            "conflict name: `user`",
+            Conflict::Name("user".to_string()).to_string()
+        );
         assert_eq!(
             "conflict method: `GET` on `/` is already set",
             Conflict::Method("/".to_string(), http::Method::GET).to_string()
@@ -95,5 +108,9 @@ mod tests {
             "missing variable on path /:",
             RouterError::MissingVariable("/:".to_string()).to_string()
         );
+        assert_eq!(
+            "name `user` is given before any endpoint is registered",
+            RouterError::UnboundName("user".to_string()).to_string()
+        );
     }
 }
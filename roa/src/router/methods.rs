@@ -0,0 +1,90 @@
+use super::endpoints::{allow_value, method_not_allowed};
+use super::err::Conflict;
+use crate::http::header::ALLOW;
+use crate::http::{Method, StatusCode};
+use crate::{Boxed, Context, Endpoint, Result};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Endpoints on the same path, dispatched by http method.
+pub struct Methods<S> {
+    /// Endpoint accepting any method.
+    any: Option<Arc<Boxed<S>>>,
+    endpoints: HashMap<Method, Arc<Boxed<S>>>,
+}
+
+impl<S> Methods<S>
+where
+    S: 'static,
+{
+    /// Construct an empty set.
+    pub fn new() -> Self {
+        Self {
+            any: None,
+            endpoints: HashMap::new(),
+        }
+    }
+
+    /// Insert an endpoint on methods, `None` means any method.
+    pub fn insert(
+        &mut self,
+        path: &str,
+        methods: Option<&[Method]>,
+        endpoint: Boxed<S>,
+    ) -> std::result::Result<(), Conflict> {
+        let endpoint = Arc::new(endpoint);
+        match methods {
+            None => {
+                if self.any.is_some() || !self.endpoints.is_empty() {
+                    return Err(Conflict::Path(path.to_string()));
+                }
+                self.any = Some(endpoint);
+            }
+            Some(methods) => {
+                if self.any.is_some() {
+                    return Err(Conflict::Path(path.to_string()));
+                }
+                for method in methods {
+                    if self
+                        .endpoints
+                        .insert(method.clone(), endpoint.clone())
+                        .is_some()
+                    {
+                        return Err(Conflict::Method(path.to_string(), method.clone()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a method is allowed.
+    /// HEAD is allowed if GET is set and OPTIONS is always allowed.
+    fn allowed(&self, method: &Method) -> bool {
+        self.endpoints.contains_key(method)
+            || (*method == Method::HEAD && self.endpoints.contains_key(&Method::GET))
+            || *method == Method::OPTIONS
+    }
+
+    /// Dispatch request by method.
+    pub async fn call(&self, ctx: &mut Context<S>) -> Result {
+        if let Some(endpoint) = &self.any {
+            return endpoint.call(ctx).await;
+        }
+        if let Some(endpoint) = self.endpoints.get(ctx.method()) {
+            return endpoint.call(ctx).await;
+        }
+        if *ctx.method() == Method::HEAD {
+            if let Some(endpoint) = self.endpoints.get(&Method::GET) {
+                return endpoint.call(ctx).await;
+            }
+        }
+        let allow = allow_value(|method| self.allowed(method));
+        if *ctx.method() == Method::OPTIONS {
+            ctx.resp.status = StatusCode::NO_CONTENT;
+            ctx.resp.headers.insert(ALLOW, allow);
+            return Ok(());
+        }
+        method_not_allowed(ctx, allow)
+    }
+}
@@ -1,6 +1,8 @@
 use super::{Conflict, RouterError};
+use lazy_static::lazy_static;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use regex::{escape, Captures, Regex};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::convert::AsRef;
 use std::str::FromStr;
 
@@ -10,6 +12,22 @@ const WILDCARD: &str = r"\*\{(?P<var>\w*)\}";
 /// Match pattern /:variable/
 const VARIABLE: &str = r"/:(?P<var>\w*)/";
 
+lazy_static! {
+    static ref WILDCARD_RE: Regex = must_build(WILDCARD);
+    static ref VARIABLE_RE: Regex = must_build(VARIABLE);
+    static ref SEGMENT_VARIABLE_RE: Regex = must_build(r"^:\w+$");
+}
+
+/// Characters to encode in value of segment variable.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Characters to encode in value of wildcard, which may contain '/'.
+const WILDCARD_VALUE: &AsciiSet = &SEGMENT.remove(b'/');
+
 /// {/path path/ /path/} => /path/
 pub fn standardize_path(raw_path: &str) -> String {
     format!("/{}/", raw_path.trim_matches('/'))
@@ -26,6 +44,26 @@ pub fn join_path<'a>(paths: impl 'a + AsRef<[&'a str]>) -> String {
         .join("/")
 }
 
+/// Whether a segment is a variable like `:id`.
+pub fn is_variable(segment: &str) -> bool {
+    SEGMENT_VARIABLE_RE.is_match(segment)
+}
+
+/// Whether a segment contains wildcards like `*{path}`.
+pub fn has_wildcard(segment: &str) -> bool {
+    WILDCARD_RE.is_match(segment)
+}
+
+/// Erase names of variables, `/:id/*{path}/` => `/:/*{}/`.
+pub fn erase_variables(path: &str) -> String {
+    WILDCARD_RE
+        .replace_all(path, "*{}")
+        .split('/')
+        .map(|segment| if is_variable(segment) { ":" } else { segment })
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
 /// Build pattern.
 fn must_build(pattern: &str) -> Regex {
     Regex::new(pattern).unwrap_or_else(|err| {
@@ -68,15 +106,60 @@ impl FromStr for Path {
     }
 }
 
+impl Path {
+    /// Fill variables into path, `/user/:id/` => `/user/0`.
+    ///
+    /// Values are percent-encoded, variables not in `vars` are kept.
+    pub fn fill(&self, vars: &HashMap<&str, &str>) -> String {
+        let raw = match self {
+            Path::Static(path) => path,
+            Path::Dynamic(regex_path) => &regex_path.raw,
+        };
+
+        // fill wildcards
+        let mut path = String::new();
+        let mut rest = raw.as_str();
+        while let Some(start) = rest.find("*{") {
+            let end = match rest[start..].find('}') {
+                Some(offset) => start + offset,
+                None => break,
+            };
+            match vars.get(&rest[start + 2..end]) {
+                Some(value) => {
+                    path.push_str(&rest[..start]);
+                    path.extend(utf8_percent_encode(value, WILDCARD_VALUE));
+                }
+                None => path.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        path.push_str(rest);
+
+        // fill segment variables
+        let path = path
+            .split('/')
+            .map(|segment| match vars.get(segment.get(1..).unwrap_or("")) {
+                Some(value) if is_variable(segment) => {
+                    utf8_percent_encode(value, SEGMENT).to_string()
+                }
+                _ => segment.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join("/");
+        match path.trim_end_matches('/') {
+            "" => "/".to_string(),
+            path => path.to_string(),
+        }
+    }
+}
+
 fn path_to_regexp(path: &str) -> Result<Option<(String, HashSet<String>)>, RouterError> {
     let mut pattern = escape(path);
     let mut vars = HashSet::new();
-    let wildcard_re = must_build(WILDCARD);
-    let variable_re = must_build(VARIABLE);
-    let wildcards: Vec<Captures> = wildcard_re.captures_iter(path).collect();
+    let wildcards: Vec<Captures> = WILDCARD_RE.captures_iter(path).collect();
     let variable_template = path.replace('/', "//"); // to match continuous variables like /:year/:month/:day/
     let variables: Vec<Captures> =
-        variable_re.captures_iter(&variable_template).collect();
+        VARIABLE_RE.captures_iter(&variable_template).collect();
     if wildcards.is_empty() && variables.is_empty() {
         Ok(None)
     } else {
@@ -0,0 +1,178 @@
+use super::methods::Methods;
+use super::path::{has_wildcard, is_variable, Path, RegexPath};
+use super::RouterError;
+use crate::http::Method;
+use crate::Boxed;
+use std::collections::HashMap;
+
+/// Endpoints registered on a path pattern.
+pub struct Route<S> {
+    /// Standardized raw path pattern.
+    pub raw: String,
+    pub methods: Methods<S>,
+}
+
+/// A `:variable` segment.
+struct Param<S> {
+    name: String,
+    node: Node<S>,
+}
+
+/// A node of route tree, matching one segment of path.
+///
+/// Segments are matched in priority: static > `:variable` > `*{wildcard}`.
+/// Variables with different names at the same position, like `/:id/` and `/:name/post/`,
+/// are different branches, tried in the order they are registered in.
+/// A wildcard may span multiple segments,
+/// so it matches the rest of path with a regular expression.
+pub struct Node<S> {
+    route: Option<Route<S>>,
+    statics: HashMap<String, Node<S>>,
+    params: Vec<Param<S>>,
+    wildcards: Vec<(RegexPath, Route<S>)>,
+}
+
+/// Split the first segment of a standardized path like `/user/0/`.
+fn split_segment(path: &str) -> Option<(&str, &str)> {
+    let path = path.get(1..)?;
+    let index = path.find('/')?;
+    Some((&path[..index], &path[index..]))
+}
+
+impl<S> Node<S>
+where
+    S: 'static,
+{
+    /// Construct an empty node.
+    pub fn new() -> Self {
+        Self {
+            route: None,
+            statics: HashMap::new(),
+            params: Vec::new(),
+            wildcards: Vec::new(),
+        }
+    }
+
+    /// Insert endpoint by a standardized path, `None` methods means any method.
+    pub fn insert(
+        &mut self,
+        raw: &str,
+        methods: Option<&[Method]>,
+        endpoint: Boxed<S>,
+    ) -> Result<(), RouterError> {
+        // validate variables of the whole path.
+        raw.parse::<Path>()?;
+        self.insert_rest(raw, raw, methods, endpoint)
+    }
+
+    fn insert_rest(
+        &mut self,
+        raw: &str,
+        rest: &str,
+        methods: Option<&[Method]>,
+        endpoint: Boxed<S>,
+    ) -> Result<(), RouterError> {
+        let (segment, next) = match split_segment(rest) {
+            None => {
+                let route = self.route.get_or_insert_with(|| Route {
+                    raw: raw.to_string(),
+                    methods: Methods::new(),
+                });
+                return Ok(route.methods.insert(raw, methods, endpoint)?);
+            }
+            Some(pair) => pair,
+        };
+
+        if has_wildcard(segment) {
+            return self.insert_wildcard(raw, rest, methods, endpoint);
+        }
+
+        if is_variable(segment) {
+            let name = &segment[1..];
+            let index = match self.params.iter().position(|param| param.name == name) {
+                Some(index) => index,
+                None => {
+                    self.params.push(Param {
+                        name: name.to_string(),
+                        node: Node::new(),
+                    });
+                    self.params.len() - 1
+                }
+            };
+            return self.params[index]
+                .node
+                .insert_rest(raw, next, methods, endpoint);
+        }
+
+        self.statics
+            .entry(segment.to_string())
+            .or_insert_with(Node::new)
+            .insert_rest(raw, next, methods, endpoint)
+    }
+
+    fn insert_wildcard(
+        &mut self,
+        raw: &str,
+        rest: &str,
+        methods: Option<&[Method]>,
+        endpoint: Boxed<S>,
+    ) -> Result<(), RouterError> {
+        let regex_path = match rest.parse()? {
+            Path::Dynamic(regex_path) => regex_path,
+            Path::Static(path) => {
+                unreachable!("path `{}` with wildcard must be dynamic", path)
+            }
+        };
+        for (existing, route) in self.wildcards.iter_mut() {
+            if existing.raw == regex_path.raw {
+                return Ok(route.methods.insert(raw, methods, endpoint)?);
+            }
+        }
+        let mut route = Route {
+            raw: raw.to_string(),
+            methods: Methods::new(),
+        };
+        route.methods.insert(raw, methods, endpoint)?;
+        self.wildcards.push((regex_path, route));
+        Ok(())
+    }
+
+    /// Find route by a standardized path, push captured variables.
+    pub fn find<'a>(
+        &'a self,
+        path: &str,
+        vars: &mut Vec<(String, String)>,
+    ) -> Option<&'a Route<S>> {
+        match split_segment(path) {
+            None => return self.route.as_ref(),
+            Some((segment, next)) => {
+                if let Some(route) = self
+                    .statics
+                    .get(segment)
+                    .and_then(|node| node.find(next, vars))
+                {
+                    return Some(route);
+                }
+                if !segment.is_empty() && !segment.contains(char::is_whitespace) {
+                    for param in self.params.iter() {
+                        let len = vars.len();
+                        vars.push((param.name.clone(), segment.to_string()));
+                        if let Some(route) = param.node.find(next, vars) {
+                            return Some(route);
+                        }
+                        vars.truncate(len);
+                    }
+                }
+            }
+        }
+        for (regex_path, route) in self.wildcards.iter() {
+            if let Some(cap) = regex_path.re.captures(path) {
+                for var in regex_path.vars.iter() {
+                    vars.push((var.to_string(), cap[var.as_str()].to_string()));
+                }
+                return Some(route);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,776 @@
+//! This module provides a middleware `SessionManager`, a context extension `SessionGetter`
+//! and session stores `MemoryStore` and `CookieStore`.
+//!
+//! The session id is delivered by a signed cookie,
+//! sessions are loaded before handling requests and saved lazily after that.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::cookie::cookie_parser;
+//! use roa::session::{Key, MemoryStore, SessionManager};
+//! use roa::preload::*;
+//! use roa::{App, Context};
+//!
+//! async fn login(ctx: &mut Context) -> roa::Result {
+//!     let session = ctx.session()?;
+//!     // rotate session id on privilege changes.
+//!     session.regenerate();
+//!     session.set("user", &"Hexilee")?;
+//!     Ok(())
+//! }
+//!
+//! async fn whoami(ctx: &mut Context) -> roa::Result {
+//!     let user: Option<String> = ctx.session()?.get("user")?;
+//!     ctx.write(user.unwrap_or_else(|| "anonymous".to_string()));
+//!     Ok(())
+//! }
+//!
+//! let app = App::new()
+//!     .gate(cookie_parser)
+//!     .gate(SessionManager::new(Key::generate(), MemoryStore::new()))
+//!     .end(whoami);
+//! ```
+
+pub use crate::cookie::Key;
+
+use crate::cookie::{Cookie, CookieGetter, CookieSetter};
+use crate::http::StatusCode;
+use crate::{async_trait, Context, Middleware, Next, Result, Status};
+use cookie::{CookieJar, SameSite};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A private scope to store session in Context::storage.
+struct SessionScope;
+
+/// Length of session id.
+const ID_LENGTH: usize = 32;
+
+/// Max size of a cookie.
+const COOKIE_SIZE_LIMIT: usize = 4096;
+
+/// Lifetime of sessions without max age in stores.
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Number of sessions to trigger the first sweep of `MemoryStore`.
+const MIN_SWEEP: usize = 1024;
+
+/// A session record.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Record {
+    /// Session id.
+    pub id: String,
+
+    /// Session data.
+    pub data: Map<String, Value>,
+}
+
+/// A storage of sessions.
+#[async_trait(?Send)]
+pub trait SessionStore: 'static + Sync + Send {
+    /// Load a session by the value of session cookie, return `None` if it's missing or expired.
+    async fn load(&self, value: &str) -> Result<Option<Record>>;
+
+    /// Save a session, return the value of session cookie.
+    ///
+    /// The session should expire after `max_age` if it's set.
+    async fn save(&self, record: &Record, max_age: Option<Duration>) -> Result<String>;
+
+    /// Destroy a session by id.
+    async fn destroy(&self, id: &str) -> Result;
+}
+
+/// A middleware to load and save sessions.
+///
+/// The session cookie is signed by `key`,
+/// this middleware must be used in downstream of middleware `cookie_parser`.
+///
+/// Sessions are saved only if they are modified or touched and the downstream succeeds.
+///
+/// The expiration is counted from the last save, so it's an absolute timeout by default:
+/// a session which is only read expires after max age, or `ttl` of the store,
+/// even if the user keeps active. Enable `rolling` or call `Session::touch`
+/// to extend the expiration on access.
+pub struct SessionManager<T> {
+    key: Key,
+    store: T,
+    name: String,
+    path: String,
+    max_age: Option<Duration>,
+    secure: bool,
+    same_site: SameSite,
+    rolling: bool,
+}
+
+/// A session handle, returned by `SessionGetter::session`.
+#[derive(Clone, Default)]
+pub struct Session {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// Current session id, `None` for an empty session.
+    id: Option<String>,
+    /// Ids to destroy.
+    stale: Vec<String>,
+    data: Map<String, Value>,
+    modified: bool,
+    touched: bool,
+}
+
+/// A context extension.
+/// This extension must be used in downstream of middleware `SessionManager`,
+/// otherwise you cannot get session.
+pub trait SessionGetter {
+    /// Get the session of current request.
+    fn session(&self) -> Result<Session>;
+}
+
+/// An in-memory session store.
+///
+/// Sessions without max age expire after `ttl` since they are saved,
+/// and expired sessions are swept when the number of sessions doubles.
+#[derive(Debug)]
+pub struct MemoryStore {
+    sessions: Mutex<Sessions>,
+    ttl: Duration,
+}
+
+/// Session data and expiration, indexed by id.
+#[derive(Debug, Default)]
+struct Sessions {
+    entries: HashMap<String, (Map<String, Value>, Instant)>,
+    sweep_at: usize,
+}
+
+/// A session store keeping the whole session in an encrypted cookie.
+///
+/// The expiration is sealed into the cookie, sessions without max age expire after `ttl`.
+/// Sessions cannot be revoked on server: `Session::destroy` only removes the cookie
+/// from browser, a copy of the cookie is valid until it expires.
+/// Their size is limited by cookie as well.
+pub struct CookieStore {
+    key: Key,
+    ttl: Duration,
+}
+
+/// Generate a random session id.
+fn generate_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(ID_LENGTH)
+        .collect()
+}
+
+/// Throw a internal server error.
+#[inline]
+fn manager_not_set() -> Status {
+    Status::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "middleware `SessionManager` is not set correctly",
+        false,
+    )
+}
+
+impl<T: SessionStore> SessionManager<T> {
+    /// Construct a session manager with signing key and store.
+    pub fn new(key: Key, store: T) -> Self {
+        Self {
+            key,
+            store,
+            name: "roa.sid".to_string(),
+            path: "/".to_string(),
+            max_age: None,
+            secure: false,
+            same_site: SameSite::Lax,
+            rolling: false,
+        }
+    }
+
+    /// Extend the expiration of sessions on every request, false by default.
+    ///
+    /// Loaded sessions are saved and session cookie is set again
+    /// even if they are not modified.
+    pub fn rolling(mut self, rolling: bool) -> Self {
+        self.rolling = rolling;
+        self
+    }
+
+    /// Set name of session cookie, "roa.sid" by default.
+    pub fn name(mut self, name: impl ToString) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Set path of session cookie, "/" by default.
+    pub fn path(mut self, path: impl ToString) -> Self {
+        self.path = path.to_string();
+        self
+    }
+
+    /// Set max age of sessions, sessions live until browser closes by default.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Set "Secure" attribute of session cookie, false by default.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Set "SameSite" attribute of session cookie, "Lax" by default.
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    /// Verify the signed session cookie.
+    fn verify<S>(&self, ctx: &Context<S>) -> Option<String> {
+        let cookie = ctx.cookie(&self.name)?;
+        let mut jar = CookieJar::new();
+        jar.add_original(Cookie::new(self.name.clone(), cookie.value().to_string()));
+        Some(jar.signed(&self.key).get(&self.name)?.value().to_string())
+    }
+
+    /// Build a session cookie.
+    fn cookie(&self, value: String) -> Cookie<'static> {
+        let mut cookie = Cookie::build(self.name.clone(), value)
+            .path(self.path.clone())
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(self.same_site)
+            .finish();
+        if let Some(max_age) = self.max_age {
+            cookie.set_max_age(time::Duration::seconds(max_age.as_secs() as i64));
+        }
+        cookie
+    }
+
+    /// Sign and set session cookie.
+    fn set_cookie<S>(&self, ctx: &mut Context<S>, value: String) -> Result {
+        let mut jar = CookieJar::new();
+        jar.signed(&self.key).add(self.cookie(value));
+        match jar.get(&self.name) {
+            Some(cookie) => ctx.set_cookie(cookie.clone()),
+            None => Err(Status::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "fail to sign session cookie",
+                false,
+            )),
+        }
+    }
+
+    /// Remove session cookie.
+    fn remove_cookie<S>(&self, ctx: &mut Context<S>) -> Result {
+        let mut cookie = self.cookie(String::new());
+        cookie.set_max_age(time::Duration::zero());
+        ctx.set_cookie(cookie)
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S, T: SessionStore> Middleware<'a, S> for SessionManager<T> {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let record = match self.verify(ctx) {
+            Some(value) => self.store.load(&value).await?,
+            None => None,
+        };
+        let loaded = record.is_some();
+        let session = Session::from(record);
+        if self.rolling {
+            session.touch();
+        }
+        ctx.store_scoped(SessionScope, "session", session.clone());
+        next.await?;
+
+        let (record, stale) = {
+            let mut inner = session.inner();
+            let stale = std::mem::take(&mut inner.stale);
+            let record = match &inner.id {
+                Some(id) if inner.modified || inner.touched => Some(Record {
+                    id: id.clone(),
+                    data: inner.data.clone(),
+                }),
+                _ => None,
+            };
+            (record, stale)
+        };
+        for id in stale {
+            self.store.destroy(&id).await?;
+        }
+        match record {
+            Some(record) => {
+                let value = self.store.save(&record, self.max_age).await?;
+                self.set_cookie(ctx, value)
+            }
+            None if loaded && session.id().is_none() => self.remove_cookie(ctx),
+            None => Ok(()),
+        }
+    }
+}
+
+impl From<Option<Record>> for Session {
+    fn from(record: Option<Record>) -> Self {
+        let inner = match record {
+            Some(record) => Inner {
+                id: Some(record.id),
+                data: record.data,
+                ..Default::default()
+            },
+            None => Inner::default(),
+        };
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+}
+
+impl Session {
+    /// Lock inner state, ignore poisoning as state is always consistent.
+    fn inner(&self) -> MutexGuard<'_, Inner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Id of this session, `None` if it's empty.
+    pub fn id(&self) -> Option<String> {
+        self.inner().id.clone()
+    }
+
+    /// Get a value by key, return `None` if it not exists.
+    pub fn get<T>(&self, key: &str) -> Result<Option<T>>
+    where
+        T: DeserializeOwned,
+    {
+        match self.inner().data.get(key) {
+            Some(value) => Ok(Some(serde_json::from_value(value.clone())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Set a value.
+    pub fn set<T>(&self, key: impl ToString, value: &T) -> Result
+    where
+        T: Serialize,
+    {
+        let value = serde_json::to_value(value)?;
+        let mut inner = self.inner();
+        inner.data.insert(key.to_string(), value);
+        inner.modify();
+        Ok(())
+    }
+
+    /// Remove a value by key, return it if it exists.
+    pub fn remove(&self, key: &str) -> Option<Value> {
+        let mut inner = self.inner();
+        let value = inner.data.remove(key)?;
+        inner.modify();
+        Some(value)
+    }
+
+    /// Save this session even if it's not modified, to extend its expiration.
+    ///
+    /// An empty session is not saved.
+    pub fn touch(&self) {
+        let mut inner = self.inner();
+        if inner.id.is_some() {
+            inner.touched = true;
+        }
+    }
+
+    /// Rotate session id, data is kept.
+    ///
+    /// Call it on privilege changes, like login, to prevent session fixation.
+    pub fn regenerate(&self) {
+        let mut inner = self.inner();
+        if let Some(id) = inner.id.take() {
+            inner.stale.push(id);
+        }
+        inner.modify();
+    }
+
+    /// Destroy this session, all data is cleared.
+    pub fn destroy(&self) {
+        let mut inner = self.inner();
+        if let Some(id) = inner.id.take() {
+            inner.stale.push(id);
+        }
+        inner.data.clear();
+        inner.modified = false;
+        inner.touched = false;
+    }
+}
+
+impl Inner {
+    /// Mark as modified, generate an id if it's empty.
+    fn modify(&mut self) {
+        if self.id.is_none() {
+            self.id = Some(generate_id());
+        }
+        self.modified = true;
+    }
+}
+
+impl<S> SessionGetter for Context<S> {
+    #[inline]
+    fn session(&self) -> Result<Session> {
+        match self.load_scoped::<SessionScope, Session>("session") {
+            Some(session) => Ok((*session).clone()),
+            None => Err(manager_not_set()),
+        }
+    }
+}
+
+impl MemoryStore {
+    /// Construct an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set lifetime of sessions without max age, one day by default.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Lock sessions, ignore poisoning.
+    fn sessions(&self) -> MutexGuard<'_, Sessions> {
+        self.sessions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self {
+            sessions: Mutex::default(),
+            ttl: DEFAULT_TTL,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl SessionStore for MemoryStore {
+    async fn load(&self, value: &str) -> Result<Option<Record>> {
+        let mut sessions = self.sessions();
+        match sessions.entries.get(value) {
+            Some((_, expires)) if *expires <= Instant::now() => {
+                sessions.entries.remove(value);
+                Ok(None)
+            }
+            Some((data, _)) => Ok(Some(Record {
+                id: value.to_string(),
+                data: data.clone(),
+            })),
+            None => Ok(None),
+        }
+    }
+
+    async fn save(&self, record: &Record, max_age: Option<Duration>) -> Result<String> {
+        let now = Instant::now();
+        let mut sessions = self.sessions();
+        if sessions.entries.len() >= sessions.sweep_at.max(MIN_SWEEP) {
+            sessions.entries.retain(|_, (_, expires)| *expires > now);
+            sessions.sweep_at = sessions.entries.len() * 2;
+        }
+        sessions.entries.insert(
+            record.id.clone(),
+            (record.data.clone(), now + max_age.unwrap_or(self.ttl)),
+        );
+        Ok(record.id.clone())
+    }
+
+    async fn destroy(&self, id: &str) -> Result {
+        self.sessions().entries.remove(id);
+        Ok(())
+    }
+}
+
+impl CookieStore {
+    /// Construct a store encrypting sessions by `key`.
+    pub fn new(key: Key) -> Self {
+        Self {
+            key,
+            ttl: DEFAULT_TTL,
+        }
+    }
+
+    /// Set lifetime of sessions without max age, one day by default.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+}
+
+/// Seconds since unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[async_trait(?Send)]
+impl SessionStore for CookieStore {
+    async fn load(&self, value: &str) -> Result<Option<Record>> {
+        let mut jar = CookieJar::new();
+        jar.add_original(Cookie::new("session", value.to_string()));
+        let payload = match jar.private(&self.key).get("session") {
+            Some(cookie) => cookie.value().to_string(),
+            None => return Ok(None),
+        };
+        let mut payload: Map<String, Value> = match serde_json::from_str(&payload) {
+            Ok(payload) => payload,
+            Err(_) => return Ok(None),
+        };
+        match payload
+            .remove("expires")
+            .and_then(|expires| expires.as_u64())
+        {
+            Some(expires) if expires > unix_now() => (),
+            _ => return Ok(None),
+        }
+        match (payload.remove("id"), payload.remove("data")) {
+            (Some(Value::String(id)), Some(Value::Object(data))) => {
+                Ok(Some(Record { id, data }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn save(&self, record: &Record, max_age: Option<Duration>) -> Result<String> {
+        let expires = unix_now() + max_age.unwrap_or(self.ttl).as_secs();
+        let mut payload = Map::new();
+        payload.insert("id".to_string(), Value::String(record.id.clone()));
+        payload.insert("data".to_string(), Value::Object(record.data.clone()));
+        payload.insert("expires".to_string(), Value::from(expires));
+        let mut jar = CookieJar::new();
+        jar.private(&self.key)
+            .add(Cookie::new("session", serde_json::to_string(&payload)?));
+        let value = match jar.get("session") {
+            Some(cookie) => cookie.value().to_string(),
+            None => String::new(),
+        };
+        if value.is_empty() || value.len() > COOKIE_SIZE_LIMIT {
+            return Err(Status::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!(
+                    "session is too large to store in cookie, limit is {} bytes",
+                    COOKIE_SIZE_LIMIT
+                ),
+                false,
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Cookie sessions cannot be revoked on server, they are valid until expiration.
+    async fn destroy(&self, _id: &str) -> Result {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{
+        CookieStore, Key, MemoryStore, Record, SessionManager, SessionStore, MIN_SWEEP,
+    };
+    use crate::cookie::cookie_parser;
+    use crate::http::header::{COOKIE, SET_COOKIE};
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::testing::{TestClient, TestResponse};
+    use crate::{App, Context};
+    use std::time::Duration;
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        let session = ctx.session()?;
+        match ctx.uri().path() {
+            "/login" => {
+                session.regenerate();
+                session.set("user", &"Hexilee")?;
+            }
+            "/logout" => session.destroy(),
+            _ => (),
+        }
+        let user: Option<String> = session.get("user")?;
+        ctx.write(user.unwrap_or_else(|| "anonymous".to_string()));
+        Ok(())
+    }
+
+    /// Get "name=value" of session cookie.
+    fn session_cookie(resp: &TestResponse) -> Option<String> {
+        let cookie = resp.headers.get(SET_COOKIE)?.to_str().ok()?;
+        Some(cookie.split(';').next()?.to_string())
+    }
+
+    async fn login_and_logout<T: SessionStore>(
+        store: T,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let app = App::new()
+            .gate(cookie_parser)
+            .gate(SessionManager::new(Key::generate(), store))
+            .end(end);
+        let client = TestClient::new(&app);
+
+        // empty session is not saved.
+        let resp = client.get("/").send().await?;
+        assert!(resp.headers.get(SET_COOKIE).is_none());
+        assert_eq!("anonymous", resp.text().await?);
+
+        let resp = client.get("/login").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        let cookie = session_cookie(&resp).expect("session cookie should be set");
+        assert_eq!("Hexilee", resp.text().await?);
+
+        // unmodified session is not saved again.
+        let resp = client
+            .get("/")
+            .header(COOKIE, cookie.as_str())
+            .send()
+            .await?;
+        assert!(resp.headers.get(SET_COOKIE).is_none());
+        assert_eq!("Hexilee", resp.text().await?);
+
+        // tampered cookie is ignored.
+        let resp = client
+            .get("/")
+            .header(COOKIE, format!("{}x", cookie))
+            .send()
+            .await?;
+        assert_eq!("anonymous", resp.text().await?);
+
+        // id rotates on login.
+        let resp = client
+            .get("/login")
+            .header(COOKIE, cookie.as_str())
+            .send()
+            .await?;
+        let rotated = session_cookie(&resp).expect("session cookie should be set");
+        assert_ne!(cookie, rotated);
+
+        let resp = client
+            .get("/logout")
+            .header(COOKIE, rotated.as_str())
+            .send()
+            .await?;
+        assert!(resp.headers[SET_COOKIE].to_str()?.contains("Max-Age=0"));
+        assert_eq!("anonymous", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn memory_store() -> Result<(), Box<dyn std::error::Error>> {
+        login_and_logout(MemoryStore::new()).await
+    }
+
+    #[tokio::test]
+    async fn cookie_store() -> Result<(), Box<dyn std::error::Error>> {
+        login_and_logout(CookieStore::new(Key::generate())).await
+    }
+
+    #[tokio::test]
+    async fn rolling() -> Result<(), Box<dyn std::error::Error>> {
+        let app = App::new()
+            .gate(cookie_parser)
+            .gate(SessionManager::new(Key::generate(), MemoryStore::new()).rolling(true))
+            .end(end);
+        let client = TestClient::new(&app);
+
+        // empty session is still not saved.
+        let resp = client.get("/").send().await?;
+        assert!(resp.headers.get(SET_COOKIE).is_none());
+
+        let cookie = session_cookie(&client.get("/login").send().await?).unwrap();
+
+        // unmodified session is saved again to extend expiration.
+        let resp = client
+            .get("/")
+            .header(COOKIE, cookie.as_str())
+            .send()
+            .await?;
+        assert_eq!(Some(cookie), session_cookie(&resp));
+        assert_eq!("Hexilee", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stale_session() -> Result<(), Box<dyn std::error::Error>> {
+        let store = MemoryStore::new();
+        let app = App::new()
+            .gate(cookie_parser)
+            .gate(SessionManager::new(Key::generate(), store))
+            .end(end);
+        let client = TestClient::new(&app);
+        let cookie = session_cookie(&client.get("/login").send().await?).unwrap();
+        let rotated = session_cookie(
+            &client
+                .get("/login")
+                .header(COOKIE, cookie.as_str())
+                .send()
+                .await?,
+        )
+        .unwrap();
+
+        // old id is destroyed after rotation.
+        let resp = client
+            .get("/")
+            .header(COOKIE, cookie.as_str())
+            .send()
+            .await?;
+        assert_eq!("anonymous", resp.text().await?);
+        let resp = client
+            .get("/")
+            .header(COOKIE, rotated.as_str())
+            .send()
+            .await?;
+        assert_eq!("Hexilee", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expired_cookie() -> crate::Result {
+        let store = CookieStore::new(Key::generate());
+        let record = Record {
+            id: "id".to_string(),
+            ..Default::default()
+        };
+        let value = store.save(&record, Some(Duration::from_secs(60))).await?;
+        assert_eq!(Some(record.clone()), store.load(&value).await?);
+        let value = store.save(&record, Some(Duration::from_secs(0))).await?;
+        assert_eq!(None, store.load(&value).await?);
+
+        let store = CookieStore::new(Key::generate()).ttl(Duration::from_secs(0));
+        let value = store.save(&record, None).await?;
+        assert_eq!(None, store.load(&value).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sweep_memory_store() -> crate::Result {
+        let store = MemoryStore::new().ttl(Duration::from_secs(0));
+        for index in 0..=MIN_SWEEP {
+            let record = Record {
+                id: index.to_string(),
+                ..Default::default()
+            };
+            store.save(&record, None).await?;
+        }
+        assert_eq!(1, store.sessions().entries.len());
+        assert_eq!(None, store.load(&MIN_SWEEP.to_string()).await?);
+        Ok(())
+    }
+}
@@ -38,12 +38,37 @@
 //! Ok(())
 //! # }
 //! ```
+//!
+//! ### Graceful shutdown
+//!
+//! ```
+//! use roa::{App, Context, Result};
+//! use roa::tcp::{Listener, Shutdown};
+//! use std::io;
+//!
+//! async fn end(_ctx: &mut Context) -> Result {
+//!     Ok(())
+//! }
+//!
+//! # fn main() -> io::Result<()> {
+//! let app = App::new().end(end);
+//! let shutdown = Shutdown::new();
+//! let (addr, server) = app.bind_graceful("127.0.0.1:0", &shutdown)?;
+//! // call `shutdown.shutdown()` on signal
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
 
 mod incoming;
 mod listener;
+mod shutdown;
 
 #[doc(inline)]
 pub use incoming::TcpIncoming;
 
 #[doc(inline)]
 pub use listener::Listener;
+
+#[doc(inline)]
+pub use shutdown::{Graceful, Shutdown, Signal};
@@ -1,4 +1,4 @@
-use super::TcpIncoming;
+use super::{Graceful, Shutdown, TcpIncoming};
 use async_std::sync::Arc;
 use roa_core::{App, Endpoint, Executor, Server, State};
 use std::net::{SocketAddr, ToSocketAddrs};
@@ -21,6 +21,14 @@ pub trait Listener {
         callback: impl Fn(SocketAddr),
     ) -> std::io::Result<Self::Server>;
 
+    /// Listen on a socket addr, return a server can be shut down gracefully by `shutdown`,
+    /// and the real addr it binds.
+    fn bind_graceful(
+        self,
+        addr: impl ToSocketAddrs,
+        shutdown: &Shutdown,
+    ) -> std::io::Result<(SocketAddr, Graceful)>;
+
     /// Listen on an unused port of 127.0.0.1, return a server and the real addr it binds.
     /// ### Example
     /// ```rust
@@ -71,6 +79,16 @@ where
         Ok(server)
     }
 
+    fn bind_graceful(
+        self,
+        addr: impl ToSocketAddrs,
+        shutdown: &Shutdown,
+    ) -> std::io::Result<(SocketAddr, Graceful)> {
+        let exec = self.exec().clone();
+        let (addr, server) = self.bind(addr)?;
+        Ok((addr, shutdown.graceful(exec, server)))
+    }
+
     fn run(self) -> std::io::Result<(SocketAddr, Self::Server)> {
         self.bind("127.0.0.1:0")
     }
@@ -0,0 +1,294 @@
+use futures::future::{select, Either};
+use futures::task::Waker;
+use futures_timer::Delay;
+use log::warn;
+use roa_core::{Accept, AddrStream, App, Endpoint, Executor, Server, State};
+use std::collections::hash_map::{Entry, HashMap};
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{self, Poll};
+use std::time::Duration;
+
+/// Default deadline of graceful shutdown.
+const DEFAULT_DEADLINE: Duration = Duration::from_secs(30);
+
+/// A handle to shut down servers gracefully.
+///
+/// When `Shutdown::shutdown` is called, servers bound with this handle stop accepting
+/// new connections, then wait for in-flight requests and spawned tasks (like websocket tasks)
+/// until they are complete or the deadline is reached.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::{App, Context, Result};
+/// use roa::tcp::{Listener, Shutdown};
+/// use async_std::task::spawn;
+/// use std::time::Duration;
+///
+/// async fn end(_ctx: &mut Context) -> Result {
+///     Ok(())
+/// }
+///
+/// #[async_std::main]
+/// async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+///     let shutdown = Shutdown::new().deadline(Duration::from_secs(10));
+///     let (addr, server) = App::new().end(end).bind_graceful("127.0.0.1:0", &shutdown)?;
+///     let handle = spawn(server);
+///     // on signal...
+///     shutdown.shutdown();
+///     handle.await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Shutdown {
+    deadline: Duration,
+    trigger: Arc<Trigger>,
+}
+
+/// A future that resolves when shutdown is triggered.
+pub struct Signal {
+    trigger: Arc<Trigger>,
+    key: usize,
+}
+
+/// A server that can be shut down gracefully.
+pub struct Graceful(
+    Pin<Box<dyn 'static + Send + Future<Output = Result<(), hyper::Error>>>>,
+);
+
+#[derive(Default)]
+struct Trigger {
+    triggered: AtomicBool,
+    next_key: AtomicUsize,
+    wakers: Mutex<HashMap<usize, Waker>>,
+}
+
+impl Shutdown {
+    /// Construct a handle with default deadline (30s).
+    pub fn new() -> Self {
+        Self {
+            deadline: DEFAULT_DEADLINE,
+            trigger: Arc::new(Trigger::default()),
+        }
+    }
+
+    /// Set the deadline of draining connections and tasks.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Trigger shutdown.
+    pub fn shutdown(&self) {
+        if !self.trigger.triggered.swap(true, Ordering::SeqCst) {
+            let wakers = std::mem::take(&mut *self.trigger.wakers.lock().unwrap());
+            for (_, waker) in wakers {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Whether shutdown is triggered.
+    pub fn is_shutdown(&self) -> bool {
+        self.trigger.triggered.load(Ordering::SeqCst)
+    }
+
+    /// Get a future that resolves when shutdown is triggered.
+    pub fn signal(&self) -> Signal {
+        Signal {
+            trigger: self.trigger.clone(),
+            key: self.trigger.next_key.fetch_add(1, Ordering::SeqCst),
+        }
+    }
+
+    /// Wrap a server of app to shut down gracefully.
+    ///
+    /// The executor should be the one of app.
+    pub fn graceful<I, IO, S, E>(
+        &self,
+        exec: Executor,
+        server: Server<I, App<S, Arc<E>>, Executor>,
+    ) -> Graceful
+    where
+        S: State,
+        E: for<'a> Endpoint<'a, S>,
+        IO: 'static + Send + Sync + Unpin + futures::AsyncRead + futures::AsyncWrite,
+        I: 'static + Send + Accept<Conn = AddrStream<IO>>,
+        I::Error: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let deadline = self.deadline;
+        let timer = self.signal();
+        let draining = Box::pin(async move {
+            server.with_graceful_shutdown(timer).await?;
+            exec.idle().await;
+            Ok(())
+        });
+        let signal = self.signal();
+        let timeout = Box::pin(async move {
+            signal.await;
+            Delay::new(deadline).await
+        });
+        Graceful(Box::pin(async move {
+            match select(draining, timeout).await {
+                Either::Left((result, _)) => result,
+                Either::Right(_) => {
+                    warn!("graceful shutdown timeout after {:?}", deadline);
+                    Ok(())
+                }
+            }
+        }))
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Future for Signal {
+    type Output = ();
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        if self.trigger.triggered.load(Ordering::SeqCst) {
+            return Poll::Ready(());
+        }
+        // store a single waker for each signal.
+        match self.trigger.wakers.lock().unwrap().entry(self.key) {
+            Entry::Occupied(mut entry) => {
+                if !entry.get().will_wake(cx.waker()) {
+                    entry.insert(cx.waker().clone());
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(cx.waker().clone());
+            }
+        }
+        // check again in case shutdown is triggered before waker is registered.
+        if self.trigger.triggered.load(Ordering::SeqCst) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Signal {
+    #[inline]
+    fn drop(&mut self) {
+        self.trigger.wakers.lock().unwrap().remove(&self.key);
+    }
+}
+
+impl Future for Graceful {
+    type Output = Result<(), hyper::Error>;
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        self.0.as_mut().poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Shutdown;
+    use crate::http::StatusCode;
+    use crate::tcp::Listener;
+    use crate::{App, Context};
+    use async_std::net::TcpStream;
+    use async_std::task::spawn;
+    use futures::channel::mpsc::{unbounded, UnboundedSender};
+    use futures::task::{noop_waker, Context as TaskContext};
+    use futures::{AsyncReadExt, AsyncWriteExt, StreamExt};
+    use futures_timer::Delay;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn signal_waker() {
+        let shutdown = Shutdown::new();
+        let mut signal = shutdown.signal();
+        let waker = noop_waker();
+        let mut cx = TaskContext::from_waker(&waker);
+        for _ in 0..10 {
+            assert!(Pin::new(&mut signal).poll(&mut cx).is_pending());
+        }
+        assert_eq!(1, shutdown.trigger.wakers.lock().unwrap().len());
+        drop(signal);
+        assert!(shutdown.trigger.wakers.lock().unwrap().is_empty());
+
+        let mut signal = shutdown.signal();
+        assert!(Pin::new(&mut signal).poll(&mut cx).is_pending());
+        shutdown.shutdown();
+        assert!(Pin::new(&mut signal).poll(&mut cx).is_ready());
+    }
+
+    #[async_std::test]
+    async fn shutdown_idle() -> Result<(), Box<dyn std::error::Error>> {
+        let shutdown = Shutdown::new();
+        let (_, server) = App::new().end(()).bind_graceful("127.0.0.1:0", &shutdown)?;
+        let handle = spawn(server);
+        shutdown.shutdown();
+        assert!(shutdown.is_shutdown());
+        handle.await?;
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn drain_in_flight() -> Result<(), Box<dyn std::error::Error>> {
+        // notify when the request is in flight.
+        async fn slow(ctx: &mut Context<UnboundedSender<()>>) -> crate::Result {
+            ctx.unbounded_send(()).unwrap();
+            Delay::new(Duration::from_millis(100)).await;
+            ctx.resp.status = StatusCode::CREATED;
+            Ok(())
+        }
+        let shutdown = Shutdown::new();
+        let (sender, mut receiver) = unbounded();
+        let (addr, server) = App::state(sender)
+            .end(slow)
+            .bind_graceful("127.0.0.1:0", &shutdown)?;
+        let handle = spawn(server);
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await?;
+        receiver.next().await;
+        shutdown.shutdown();
+        let mut data = [0; 12];
+        stream.read_exact(&mut data).await?;
+        assert_eq!(b"HTTP/1.1 201", &data);
+        handle.await?;
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn deadline() -> Result<(), Box<dyn std::error::Error>> {
+        async fn hang(ctx: &mut Context) -> crate::Result {
+            ctx.exec.spawn(Delay::new(Duration::from_secs(10)));
+            Ok(())
+        }
+        let shutdown = Shutdown::new().deadline(Duration::from_millis(100));
+        let (addr, server) = App::new()
+            .end(hang)
+            .bind_graceful("127.0.0.1:0", &shutdown)?;
+        let handle = spawn(server);
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+            .await?;
+        let mut data = [0; 12];
+        stream.read_exact(&mut data).await?;
+        assert_eq!(b"HTTP/1.1 200", &data);
+        let start = Instant::now();
+        shutdown.shutdown();
+        handle.await?;
+        assert!(start.elapsed() < Duration::from_secs(1));
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+//! This module provides an in-process test client `TestClient`,
+//! which serves requests by `HttpService` directly, without binding any socket.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::testing::TestClient;
+//! use roa::{App, Context};
+//! use roa::http::StatusCode;
+//! use roa::preload::*;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     ctx.write("Hello, World!");
+//!     Ok(())
+//! }
+//!
+//! #[async_std::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let client = TestClient::new(&App::new().end(end));
+//!     let resp = client.get("/").send().await?;
+//!     assert_eq!(StatusCode::OK, resp.status);
+//!     assert_eq!("Hello, World!", resp.text().await?);
+//!     Ok(())
+//! }
+//! ```
+
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::http::{request, HeaderMap, Method, StatusCode, Version};
+use crate::{App, Body, Endpoint, HttpService, Request, State};
+use bytes::{Bytes, BytesMut};
+use futures::StreamExt;
+use std::convert::TryFrom;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+#[cfg(any(feature = "json", feature = "urlencoded"))]
+use crate::http::header::CONTENT_TYPE;
+
+#[cfg(any(feature = "json", feature = "urlencoded"))]
+use serde::Serialize;
+
+#[cfg(feature = "json")]
+use serde::de::DeserializeOwned;
+
+/// A client to send fake requests to an app.
+pub struct TestClient<S, E> {
+    service: HttpService<S, E>,
+}
+
+/// A builder of fake request.
+pub struct TestRequest<'a, S, E> {
+    client: &'a TestClient<S, E>,
+    builder: request::Builder,
+    body: io::Result<Bytes>,
+}
+
+/// The response returned by `TestRequest::send`.
+pub struct TestResponse {
+    /// Status code.
+    pub status: StatusCode,
+
+    /// Version of HTTP protocol.
+    pub version: Version,
+
+    /// Raw header map.
+    pub headers: HeaderMap<HeaderValue>,
+
+    /// Response body.
+    pub body: Body,
+}
+
+macro_rules! impl_client_methods {
+    ($end:ident, $method:expr, $doc:expr) => {
+        #[doc = $doc]
+        #[inline]
+        pub fn $end(&self, path: &str) -> TestRequest<'_, S, E> {
+            self.request($method, path)
+        }
+    };
+}
+
+impl<S, E> TestClient<S, E>
+where
+    S: State,
+    E: for<'a> Endpoint<'a, S>,
+{
+    /// Construct a client by an app.
+    pub fn new(app: &App<S, Arc<E>>) -> Self {
+        Self {
+            service: app.http_service(),
+        }
+    }
+
+    /// Set the fake socket addr of client, `127.0.0.1:0` by default.
+    pub fn remote_addr(mut self, addr: SocketAddr) -> Self {
+        self.service.set_remote_addr(addr);
+        self
+    }
+
+    /// Start a request by method and path.
+    ///
+    /// The path may contain a query string, like `/user?name=Hexilee`.
+    pub fn request(&self, method: Method, path: &str) -> TestRequest<'_, S, E> {
+        TestRequest {
+            client: self,
+            builder: request::Builder::new().method(method).uri(path),
+            body: Ok(Bytes::new()),
+        }
+    }
+
+    impl_client_methods!(get, Method::GET, "Start a GET request to the path.");
+    impl_client_methods!(post, Method::POST, "Start a POST request to the path.");
+    impl_client_methods!(put, Method::PUT, "Start a PUT request to the path.");
+    impl_client_methods!(patch, Method::PATCH, "Start a PATCH request to the path.");
+    impl_client_methods!(
+        delete,
+        Method::DELETE,
+        "Start a DELETE request to the path."
+    );
+    impl_client_methods!(head, Method::HEAD, "Start a HEAD request to the path.");
+    impl_client_methods!(
+        options,
+        Method::OPTIONS,
+        "Start a OPTIONS request to the path."
+    );
+}
+
+impl<S, E> TestRequest<'_, S, E>
+where
+    S: State,
+    E: for<'a> Endpoint<'a, S>,
+{
+    /// Append a request header.
+    pub fn header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<crate::http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<crate::http::Error>,
+    {
+        self.builder = self.builder.header(key, value);
+        self
+    }
+
+    /// Set raw request body.
+    pub fn body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = Ok(body.into());
+        self
+    }
+
+    /// Serialize data as request body and set "Content-Type: application/json".
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+    pub fn json<B>(mut self, data: &B) -> Self
+    where
+        B: Serialize,
+    {
+        self.body = serde_json::to_vec(data).map(Into::into).map_err(Into::into);
+        self.header(CONTENT_TYPE, "application/json")
+    }
+
+    /// Serialize data as request body and set "Content-Type: application/x-www-form-urlencoded".
+    #[cfg(feature = "urlencoded")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "urlencoded")))]
+    pub fn form<B>(mut self, data: &B) -> Self
+    where
+        B: Serialize,
+    {
+        self.body = serde_urlencoded::to_string(data)
+            .map(Into::into)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err));
+        self.header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+    }
+
+    /// Send this request to app and wait for the response.
+    pub async fn send(self) -> io::Result<TestResponse> {
+        let Self {
+            client,
+            builder,
+            body,
+        } = self;
+        let req = builder
+            .body(hyper::Body::from(body?))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let resp = client.service.clone().serve(Request::from(req)).await;
+        Ok(TestResponse {
+            status: resp.status,
+            version: resp.version,
+            headers: resp.headers,
+            body: resp.body,
+        })
+    }
+}
+
+impl TestResponse {
+    /// Read response body as bytes.
+    pub async fn bytes(mut self) -> io::Result<Bytes> {
+        let mut data = BytesMut::new();
+        while let Some(chunk) = self.body.next().await {
+            data.extend_from_slice(&chunk?);
+        }
+        Ok(data.freeze())
+    }
+
+    /// Read response body as utf-8 text.
+    pub async fn text(self) -> io::Result<String> {
+        let data = self.bytes().await?;
+        String::from_utf8(data.to_vec())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Read response body as json.
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+    pub async fn json<B>(self) -> io::Result<B>
+    where
+        B: DeserializeOwned,
+    {
+        let data = self.bytes().await?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+}
+
+#[cfg(all(test, feature = "runtime"))]
+mod tests {
+    use super::TestClient;
+    use crate::http::header::{CONTENT_TYPE, USER_AGENT};
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::query::query_parser;
+    use crate::{throw, App, Context};
+    use serde::{Deserialize, Serialize};
+    use std::error::Error;
+
+    #[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+    struct User {
+        id: u64,
+        name: String,
+    }
+
+    #[async_std::test]
+    async fn method_path_and_headers() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            assert_eq!("/user", ctx.uri().path());
+            assert_eq!("Hexilee", &*ctx.must_query("name")?);
+            assert_eq!("roa", ctx.must_get(USER_AGENT)?);
+            let method = ctx.method().to_string();
+            ctx.write(method);
+            Ok(())
+        }
+        let client = TestClient::new(&App::new().gate(query_parser).end(test));
+        let resp = client
+            .delete("/user?name=Hexilee")
+            .header(USER_AGENT, "roa")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("text/plain", resp.headers[CONTENT_TYPE]);
+        assert_eq!("DELETE", resp.text().await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn status() -> Result<(), Box<dyn Error>> {
+        async fn test(_ctx: &mut Context) -> crate::Result {
+            throw!(StatusCode::IM_A_TEAPOT, "I'm a teapot!")
+        }
+        let client = TestClient::new(&App::new().end(test));
+        let resp = client.get("/").send().await?;
+        assert_eq!(StatusCode::IM_A_TEAPOT, resp.status);
+        assert_eq!("I'm a teapot!", resp.text().await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn remote_addr() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            assert_eq!("10.0.0.1:8080", ctx.remote_addr.to_string());
+            Ok(())
+        }
+        let client = TestClient::new(&App::new().end(test))
+            .remote_addr(([10, 0, 0, 1], 8080).into());
+        let resp = client.get("/").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[async_std::test]
+    async fn json() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let mut user: User = ctx.read_json().await?;
+            user.id += 1;
+            ctx.write_json(&user)
+        }
+        let client = TestClient::new(&App::new().end(test));
+        let user = User {
+            id: 0,
+            name: "Hexilee".to_string(),
+        };
+        let resp = client.post("/").json(&user).send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        let user: User = resp.json().await?;
+        assert_eq!(1, user.id);
+        assert_eq!("Hexilee", user.name);
+        Ok(())
+    }
+
+    #[cfg(feature = "urlencoded")]
+    #[async_std::test]
+    async fn form() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let user: User = ctx.read_form().await?;
+            assert_eq!(0, user.id);
+            assert_eq!("Hexilee", user.name);
+            Ok(())
+        }
+        let client = TestClient::new(&App::new().end(test));
+        let user = User {
+            id: 0,
+            name: "Hexilee".to_string(),
+        };
+        let resp = client.put("/").form(&user).send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+//! This module provides a middleware `Timeout`.
+//!
+//! The timer is runtime-independent, so it works on both async-std and tokio executors.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::router::Router;
+//! use roa::timeout::Timeout;
+//! use roa::{App, Context};
+//! use std::time::Duration;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     Ok(())
+//! }
+//!
+//! let router = Router::new()
+//!     .get("/", end)
+//!     .gate(Timeout::new(Duration::from_secs(60)))
+//!     .post("/upload", end);
+//! let app = App::new()
+//!     .gate(Timeout::new(Duration::from_secs(5)))
+//!     .end(router.routes("/").unwrap());
+//! ```
+
+use crate::http::StatusCode;
+use crate::{async_trait, Context, Middleware, Next, Result, Status};
+use futures::future::{select, Either};
+use futures::task::{Context as TaskContext, Poll, Waker};
+use futures_timer::Delay;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A private scope.
+struct TimeoutScope;
+
+/// The deadline and status of the outermost `Timeout`.
+#[derive(Clone)]
+struct Deadline(Arc<Mutex<Inner>>);
+
+struct Inner {
+    deadline: Instant,
+    status: StatusCode,
+    /// Waker of the racing timer.
+    waker: Option<Waker>,
+}
+
+/// A future waiting until deadline, which may be reset while waiting.
+struct Expire {
+    deadline: Deadline,
+    delay: Delay,
+    current: Instant,
+}
+
+impl Deadline {
+    fn new(deadline: Instant, status: StatusCode) -> Self {
+        Self(Arc::new(Mutex::new(Inner {
+            deadline,
+            status,
+            waker: None,
+        })))
+    }
+
+    /// Reset deadline and status, then wake the racing timer.
+    fn reset(&self, deadline: Instant, status: StatusCode) {
+        let waker = {
+            let mut inner = self.0.lock().unwrap();
+            inner.deadline = deadline;
+            inner.status = status;
+            inner.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Wait until deadline.
+    fn expire(self) -> Expire {
+        let current = self.0.lock().unwrap().deadline;
+        Expire {
+            deadline: self,
+            delay: Delay::new(current.saturating_duration_since(Instant::now())),
+            current,
+        }
+    }
+}
+
+impl Future for Expire {
+    type Output = StatusCode;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        loop {
+            let (deadline, status) = {
+                let mut inner = self.deadline.0.lock().unwrap();
+                inner.waker = Some(cx.waker().clone());
+                (inner.deadline, inner.status)
+            };
+            let now = Instant::now();
+            if deadline <= now {
+                return Poll::Ready(status);
+            }
+            if deadline != self.current {
+                self.current = deadline;
+                self.delay.reset(deadline - now);
+            }
+            if Pin::new(&mut self.delay).poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+/// A middleware to race downstream against a timer.
+///
+/// If the timer wins, return 503 SERVICE UNAVAILABLE (or the status set by `Timeout::status`),
+/// and the downstream future is dropped without being polled again.
+///
+/// Only the outermost `Timeout` races, inner ones (e.g. gated by `Router`)
+/// override its duration and status, so a route can have a longer or shorter timeout.
+/// The timer of the outermost one is reset as soon as it's overridden.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeout {
+    duration: Duration,
+    status: StatusCode,
+}
+
+impl Timeout {
+    /// Construct a middleware with duration.
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            status: StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// Set status returned on timeout, e.g. 504 GATEWAY TIMEOUT.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+#[async_trait(? Send)]
+impl<'a, S> Middleware<'a, S> for Timeout {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let deadline = Instant::now() + self.duration;
+        if let Some(outer) = ctx.load_scoped::<TimeoutScope, Deadline>("deadline") {
+            outer.reset(deadline, self.status);
+            return next.await;
+        }
+        let deadline = Deadline::new(deadline, self.status);
+        ctx.store_scoped(TimeoutScope, "deadline", deadline.clone());
+        match select(next, Box::pin(deadline.expire())).await {
+            Either::Left((result, _)) => result,
+            Either::Right((status, _)) => {
+                Err(Status::new(status, "request timeout", true))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tcp", feature = "router"))]
+mod tests {
+    use super::Timeout;
+    use crate::http::StatusCode;
+    use crate::router::Router;
+    use crate::testing::TestClient;
+    use crate::{App, Context};
+    use futures_timer::Delay;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    struct Dropped(Arc<AtomicBool>);
+
+    impl Drop for Dropped {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst)
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct State {
+        dropped: Arc<AtomicBool>,
+    }
+
+    async fn slow(ctx: &mut Context<State>) -> crate::Result {
+        let _dropped = Dropped(ctx.dropped.clone());
+        Delay::new(Duration::from_millis(200)).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn timeout() -> Result<(), Box<dyn std::error::Error>> {
+        let router = || {
+            Router::new()
+                .get("/slow", slow)
+                .gate(Timeout::new(Duration::from_secs(1)))
+                .get("/longer", slow)
+                .gate(
+                    Timeout::new(Duration::from_millis(10))
+                        .status(StatusCode::GATEWAY_TIMEOUT),
+                )
+                .get("/shorter", slow)
+        };
+        let state = State::default();
+        let app = App::state(state.clone())
+            .gate(Timeout::new(Duration::from_millis(50)))
+            .end(router().routes("/")?);
+        let client = TestClient::new(&app);
+
+        let resp = client.get("/slow").send().await?;
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, resp.status);
+        assert_eq!("request timeout", resp.text().await?);
+        assert!(state.dropped.load(Ordering::SeqCst));
+
+        let resp = client.get("/longer").send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+
+        let resp = client.get("/shorter").send().await?;
+        assert_eq!(StatusCode::GATEWAY_TIMEOUT, resp.status);
+
+        // fails long before the outer deadline, even before the endpoint completes.
+        let app = App::state(State::default())
+            .gate(Timeout::new(Duration::from_secs(60)))
+            .end(router().routes("/")?);
+        let resp = TestClient::new(&app).get("/shorter").send().await?;
+        assert_eq!(StatusCode::GATEWAY_TIMEOUT, resp.status);
+        Ok(())
+    }
+}
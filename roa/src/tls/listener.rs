@@ -1,5 +1,5 @@
 use super::{ServerConfig, TlsIncoming};
-use crate::tcp::TcpIncoming;
+use crate::tcp::{Graceful, Shutdown, TcpIncoming};
 use crate::{App, Endpoint, Executor, Server, State};
 use std::io;
 use std::net::{SocketAddr, ToSocketAddrs};
@@ -34,6 +34,15 @@ pub trait TlsListener {
         callback: impl Fn(SocketAddr),
     ) -> std::io::Result<Self::Server>;
 
+    /// Listen on a socket addr, return a server can be shut down gracefully by `shutdown`,
+    /// and the real addr it binds.
+    fn bind_tls_graceful(
+        self,
+        addr: impl ToSocketAddrs,
+        config: ServerConfig,
+        shutdown: &Shutdown,
+    ) -> std::io::Result<(SocketAddr, Graceful)>;
+
     /// Listen on an unused port of 127.0.0.1, return a server and the real addr it binds.
     /// ### Example
     /// ```rust
@@ -98,6 +107,17 @@ where
         Ok(server)
     }
 
+    fn bind_tls_graceful(
+        self,
+        addr: impl ToSocketAddrs,
+        config: ServerConfig,
+        shutdown: &Shutdown,
+    ) -> std::io::Result<(SocketAddr, Graceful)> {
+        let exec = self.exec().clone();
+        let (addr, server) = self.bind_tls(addr, config)?;
+        Ok((addr, shutdown.graceful(exec, server)))
+    }
+
     fn run_tls(
         self,
         config: ServerConfig,
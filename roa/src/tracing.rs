@@ -0,0 +1,408 @@
+//! This module provides a middleware `Tracing` and a context extension `TraceContextGetter`,
+//! which integrate roa with crate `tracing` and W3C trace context.
+//!
+//! Futures and blocking tasks spawned by `Context::exec` are attached to the request span.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::preload::*;
+//! use roa::tracing::Tracing;
+//! use roa::{App, Context};
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     // propagate trace context to outgoing calls.
+//!     let traceparent = ctx.trace_context().unwrap().traceparent();
+//!     ctx.exec
+//!         .spawn_blocking(move || println!("call with traceparent: {}", traceparent))
+//!         .await;
+//!     Ok(())
+//! }
+//!
+//! let app = App::new().gate(Tracing::new()).end(end);
+//! ```
+
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::request_id::random;
+use crate::{async_trait, Context, Middleware, Next, Result};
+use std::sync::Arc;
+use tracing_rs::field::{display, Empty};
+use tracing_rs::{info_span, Instrument};
+
+/// A private scope.
+struct TraceScope;
+
+/// Name of trace context header.
+const TRACEPARENT: &str = "traceparent";
+
+/// A W3C trace context of the request span.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    /// 128-bit trace id.
+    pub trace_id: u128,
+
+    /// 64-bit id of the span.
+    pub span_id: u64,
+
+    /// Id of the parent span, if any.
+    pub parent_id: Option<u64>,
+
+    /// Whether the trace is sampled.
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// Construct a sampled root context, with random ids.
+    pub fn root() -> Self {
+        Self {
+            trace_id: non_zero(|| (random() as u128) << 64 | random() as u128),
+            span_id: non_zero(random),
+            parent_id: None,
+            sampled: true,
+        }
+    }
+
+    /// Parse a "traceparent" header value, return `None` if it's invalid.
+    ///
+    /// The parsed context represents the remote span.
+    pub fn parse(traceparent: &str) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let version = parse_hex(parts.next()?, 2)? as u8;
+        let trace_id = parse_hex(parts.next()?, 32)?;
+        let span_id = parse_hex(parts.next()?, 16)? as u64;
+        let flags = parse_hex(parts.next()?, 2)? as u8;
+        let valid = version != 0xff
+            && (version != 0 || parts.next().is_none())
+            && trace_id != 0
+            && span_id != 0;
+        if !valid {
+            return None;
+        }
+        Some(Self {
+            trace_id,
+            span_id,
+            parent_id: None,
+            sampled: flags & 1 == 1,
+        })
+    }
+
+    /// Construct a child context, in the same trace.
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id,
+            span_id: non_zero(random),
+            parent_id: Some(self.span_id),
+            sampled: self.sampled,
+        }
+    }
+
+    /// Format as a "traceparent" header value, to propagate to outgoing calls.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.span_id, self.sampled as u8
+        )
+    }
+}
+
+/// Generate a non-zero id.
+#[inline]
+fn non_zero<T: Default + PartialEq>(generate: impl Fn() -> T) -> T {
+    loop {
+        let id = generate();
+        if id != T::default() {
+            return id;
+        }
+    }
+}
+
+/// Parse a lowercase hex field of fixed length.
+#[inline]
+fn parse_hex(field: &str, len: usize) -> Option<u128> {
+    let valid = field.len() == len
+        && field
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte));
+    if !valid {
+        return None;
+    }
+    u128::from_str_radix(field, 16).ok()
+}
+
+/// A context extension to get trace context.
+pub trait TraceContextGetter {
+    /// Get trace context of the request span, return None if middleware `Tracing` is not set.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa::preload::*;
+    /// use roa::{Context, Result};
+    ///
+    /// async fn get(ctx: &mut Context) -> Result {
+    ///     if let Some(trace) = ctx.trace_context() {
+    ///         println!("trace id: {:032x}", trace.trace_id);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    fn trace_context(&self) -> Option<Arc<TraceContext>>;
+}
+
+impl<S> TraceContextGetter for Context<S> {
+    #[inline]
+    fn trace_context(&self) -> Option<Arc<TraceContext>> {
+        Some(
+            self.load_scoped::<TraceScope, TraceContext>("context")?
+                .value(),
+        )
+    }
+}
+
+/// A middleware to open a span named "request" for each request.
+///
+/// The span has fields "method", "path", "route", "status", "error",
+/// "trace_id", "span_id" and "parent_id".
+/// Field "route" is the path pattern of matched route, recorded only with feature `router`.
+///
+/// The trace context is continued from request header "traceparent" by default,
+/// and written into response header "traceparent".
+#[derive(Debug, Clone, Copy)]
+pub struct Tracing {
+    trust_remote: bool,
+}
+
+impl Default for Tracing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tracing {
+    /// Construct a middleware continuing remote trace context.
+    pub fn new() -> Self {
+        Self { trust_remote: true }
+    }
+
+    /// Whether to continue trace context from request header "traceparent".
+    ///
+    /// Set it false to start a new trace for each request, e.g. in an edge service.
+    pub fn trust_remote(mut self, trust: bool) -> Self {
+        self.trust_remote = trust;
+        self
+    }
+}
+
+/// Path pattern of matched route.
+#[inline]
+fn matched_route<S>(_ctx: &Context<S>) -> Option<Arc<String>> {
+    #[cfg(feature = "router")]
+    {
+        use crate::router::RouterParam;
+        _ctx.matched_route()
+    }
+    #[cfg(not(feature = "router"))]
+    None
+}
+
+#[async_trait(? Send)]
+impl<'a, S> Middleware<'a, S> for Tracing {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let remote = match ctx.get(TRACEPARENT) {
+            Some(value) if self.trust_remote => TraceContext::parse(value),
+            _ => None,
+        };
+        let trace = match remote {
+            Some(remote) => remote.child(),
+            None => TraceContext::root(),
+        };
+        let span = info_span!(
+            "request",
+            method = %ctx.method(),
+            path = %ctx.uri().path(),
+            route = Empty,
+            status = Empty,
+            error = Empty,
+            trace_id = %format_args!("{:032x}", trace.trace_id),
+            span_id = %format_args!("{:016x}", trace.span_id),
+            parent_id = Empty,
+        );
+        if let Some(parent_id) = trace.parent_id {
+            span.record("parent_id", &display(format_args!("{:016x}", parent_id)));
+        }
+        ctx.resp.headers.insert(
+            HeaderName::from_static(TRACEPARENT),
+            HeaderValue::from_str(&trace.traceparent())?,
+        );
+        ctx.store_scoped(TraceScope, "context", trace);
+
+        let result = next.instrument(span.clone()).await;
+        if let Some(route) = matched_route(ctx) {
+            span.record("route", &display(route));
+        }
+        match &result {
+            Ok(_) => {
+                span.record("status", &ctx.status().as_u16());
+            }
+            Err(status) => {
+                span.record("status", &status.status_code.as_u16());
+                span.record("error", &display(&status.message));
+            }
+        }
+        result
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{TraceContext, TraceContextGetter, Tracing};
+    use crate::http::StatusCode;
+    use crate::testing::TestClient;
+    use crate::{App, Context};
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::fmt::Debug;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tracing_core::span::Current;
+    use tracing_rs::field::{Field, Visit};
+    use tracing_rs::span::{Attributes, Id, Record};
+    use tracing_rs::{Event, Metadata, Span, Subscriber};
+
+    type Fields = HashMap<String, String>;
+
+    /// A subscriber recording fields of spans.
+    #[derive(Default)]
+    struct Recorder {
+        next_id: AtomicU64,
+        spans: Arc<Mutex<HashMap<u64, (&'static Metadata<'static>, Fields)>>>,
+    }
+
+    thread_local! {
+        static STACK: RefCell<Vec<Id>> = RefCell::new(Vec::new());
+    }
+
+    struct Visitor<'a>(&'a mut Fields);
+
+    impl Visit for Visitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+            let mut fields = Fields::new();
+            span.record(&mut Visitor(&mut fields));
+            self.spans
+                .lock()
+                .unwrap()
+                .insert(id, (span.metadata(), fields));
+            Id::from_u64(id)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            if let Some((_, fields)) =
+                self.spans.lock().unwrap().get_mut(&span.into_u64())
+            {
+                values.record(&mut Visitor(fields));
+            }
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, span: &Id) {
+            STACK.with(|stack| stack.borrow_mut().push(span.clone()));
+        }
+
+        fn exit(&self, _span: &Id) {
+            STACK.with(|stack| stack.borrow_mut().pop());
+        }
+
+        fn current_span(&self) -> Current {
+            match STACK.with(|stack| stack.borrow().last().cloned()) {
+                Some(id) => {
+                    let metadata = self.spans.lock().unwrap()[&id.into_u64()].0;
+                    Current::new(id, metadata)
+                }
+                None => Current::none(),
+            }
+        }
+    }
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        let current = Span::current().id();
+        assert!(current.is_some());
+        let in_task = ctx.exec.spawn(async { Span::current().id() }).await;
+        let in_blocking = ctx.exec.spawn_blocking(|| Span::current().id()).await;
+        assert_eq!(current, in_task);
+        assert_eq!(current, in_blocking);
+        let traceparent = ctx.trace_context().unwrap().traceparent();
+        ctx.resp.write(traceparent);
+        Ok(())
+    }
+
+    #[test]
+    fn traceparent() {
+        let value = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        let trace = TraceContext::parse(value).unwrap();
+        assert_eq!(0x0af7651916cd43dd8448eb211c80319c, trace.trace_id);
+        assert_eq!(0xb7ad6b7169203331, trace.span_id);
+        assert!(trace.sampled);
+        assert_eq!(value, trace.traceparent());
+        let child = trace.child();
+        assert_eq!(trace.trace_id, child.trace_id);
+        assert_eq!(Some(trace.span_id), child.parent_id);
+        assert_ne!(trace.span_id, child.span_id);
+
+        // future version with extra fields
+        assert!(TraceContext::parse(&format!("01{}-extra", &value[2..])).is_some());
+        for invalid in &[
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-extra",
+            "ff-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-0000000000000000-01",
+            "00-0AF7651916CD43DD8448EB211C80319C-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c8031-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331",
+        ] {
+            assert!(TraceContext::parse(invalid).is_none(), "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn tracing() -> Result<(), Box<dyn std::error::Error>> {
+        let recorder = Recorder::default();
+        let spans = recorder.spans.clone();
+        tracing_rs::subscriber::set_global_default(recorder)?;
+        let client = TestClient::new(&App::new().gate(Tracing::new()).end(end));
+
+        let remote = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        let resp = client.get("/").header("traceparent", remote).send().await?;
+        assert_eq!(StatusCode::OK, resp.status);
+        let traceparent = resp.headers["traceparent"].to_str()?.to_string();
+        assert_eq!(traceparent, resp.text().await?);
+        let trace = TraceContext::parse(&traceparent).unwrap();
+        assert_eq!(0x0af7651916cd43dd8448eb211c80319c, trace.trace_id);
+        assert_ne!(0xb7ad6b7169203331, trace.span_id);
+
+        let spans = spans.lock().unwrap();
+        let (_, fields) = spans
+            .values()
+            .find(|(metadata, _)| metadata.name() == "request")
+            .unwrap();
+        assert_eq!("GET", fields["method"]);
+        assert_eq!("200", fields["status"]);
+        assert_eq!("0af7651916cd43dd8448eb211c80319c", fields["trace_id"]);
+        assert_eq!("b7ad6b7169203331", fields["parent_id"]);
+        Ok(())
+    }
+}